@@ -1,6 +1,7 @@
-use fakebeat_core::fixture::Fixture;
+use fakebeat_core::{fixture::Fixture, locale::Locale};
 
-use clap::Parser;
+use chrono::{DateTime, Utc};
+use clap::{Parser, ValueEnum};
 
 /// Generates random Elasticsearch documents based on Tera templates
 #[derive(Parser, Debug)]
@@ -10,6 +11,10 @@ pub struct Args {
     #[arg(short, long, value_parser, default_value_t = false)]
     pub generators: bool,
 
+    /// Output format of the available generators
+    #[arg(long, value_enum, default_value_t = GeneratorsFormat::Table)]
+    pub generators_format: GeneratorsFormat,
+
     /// User name
     #[arg(short, long, value_parser, default_value = "elastic")]
     pub username: String,
@@ -26,7 +31,7 @@ pub struct Args {
     #[arg(long, value_parser)]
     pub cloud: Option<String>,
 
-    /// How many documents you want generated (per template)
+    /// How many documents you want generated (per template), or sequences for scenario templates
     #[arg(short, long, value_parser, required_unless_present = "generators")]
     pub count: Vec<usize>,
 
@@ -45,6 +50,50 @@ pub struct Args {
     /// Append to the existing indices, instead of recreating them
     #[arg(short, long, value_parser, default_value_t = false)]
     pub append: bool,
+
+    /// Seed for the random generators; the same seed always produces the same documents
+    #[arg(long, value_parser)]
+    pub seed: Option<u64>,
+
+    /// Reference "now" (RFC 3339, eg. 2022-11-20T12:00:00Z) used by the date generators instead of the current time
+    #[arg(long, value_parser)]
+    pub now: Option<DateTime<Utc>>,
+
+    /// Run id exposed to the templates as `run.id`, random by default
+    #[arg(long, value_parser)]
+    pub run_id: Option<String>,
+
+    /// Run manifest file, with declarations shared by all the templates (eg. value pools)
+    #[arg(short, long, value_parser)]
+    pub manifest: Option<String>,
+
+    /// Dictionary file to register as a generator, as name=path (eg. hostnames=hosts.txt); can be repeated
+    #[arg(short, long, value_parser)]
+    pub dictionary: Vec<String>,
+
+    /// Dataset file whose rows are exposed to the templates, as name=path (eg. employees=employees.csv); can be repeated
+    #[arg(long, value_parser)]
+    pub dataset: Vec<String>,
+
+    /// Run the ingest pipeline of every template over a rendered sample and print the outcome before inserting, stopping if it fails
+    #[arg(long, value_parser, default_value_t = false)]
+    pub simulate_pipeline: bool,
+
+    /// Number of failed documents above which the run exits with an error
+    #[arg(long, value_parser, default_value_t = 0)]
+    pub max_failures: usize,
+
+    /// Locale of the faker generators (eg. fr_FR, ja_JP) for templates which do not set one
+    #[arg(long, value_parser)]
+    pub locale: Option<Locale>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GeneratorsFormat {
+    /// Human readable table
+    Table,
+    /// JSON array of the generator schemas
+    Json,
 }
 
 impl<'a> TryFrom<&'a Args> for Vec<Fixture> {
@@ -1,9 +1,15 @@
 use fakebeat_core::{
-    document_renderer, fixture::Fixture, insert_fixtures::insert_fixtures,
-    local_esclient::LocalElasticsearchBuilder, prepare_indices::prepare_indices,
+    declarations::RendererSetup,
+    fixture::Fixture,
+    generator_schema::GeneratorSchema,
+    insert_fixtures::insert_fixtures,
+    local_esclient::LocalElasticsearchBuilder,
+    locale::Locale,
+    pipelines::{install_pipelines, simulate_pipelines},
+    prepare_indices::prepare_indices,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
 use elasticsearch::{
     auth::Credentials,
@@ -14,22 +20,26 @@ use linya::{Bar, Progress};
 
 mod args;
 
-use args::Args;
+use args::{Args, GeneratorsFormat};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    if args.generators {
-        let renderer = document_renderer::DocumentRendererFactory::create_renderer();
+    let mut setup = RendererSetup {
+        seed: args.seed,
+        now: args.now,
+        locale: args.locale,
+        run_id: args.run_id.clone(),
+        dictionaries: Some(named_paths("dictionary", &args.dictionary)?),
+        datasets: Some(named_paths("dataset", &args.dataset)?),
+        manifest: args.manifest.clone(),
+    };
 
-        println!("Available generators:\n");
+    let mut renderer = setup.create_renderer().await?;
 
-        for (name, desc) in renderer.get_generators() {
-            println!("{} - {}", name, desc);
-        }
-
-        println!("");
+    if args.generators {
+        print_generators(&renderer.get_generators(), args.generators_format)?;
 
         return Ok(());
     }
@@ -53,6 +63,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .build()?
     };
 
+    println!("Run id: {}", renderer.run_id());
+
+    install_pipelines(&client, &fixtures).await?;
+
+    if args.simulate_pipeline {
+        // the samples are rendered under the same run id, by a renderer of their own
+        setup.run_id = Some(renderer.run_id().to_owned());
+
+        simulate_pipelines(&client, &fixtures, &setup).await?;
+    }
+
     println!("Setting up indices");
 
     prepare_indices(&client, &fixtures, append).await?;
@@ -68,9 +89,81 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         progress.set_and_draw(&bar, current_progress_value);
     });
 
-    insert_fixtures(&client, &fixtures, batch_size, on_progress).await?;
+    let report =
+        insert_fixtures(&client, &fixtures, batch_size, &mut renderer, on_progress).await?;
+
+    println!("{}", report);
+
+    if report.failed() > args.max_failures {
+        return Err(anyhow!(
+            "{} documents failed to insert, above --max-failures {}",
+            report.failed(),
+            args.max_failures
+        )
+        .into());
+    }
 
     println!("Done");
 
     Ok(())
 }
+
+/// Turns name=path arguments into declarations, eg. `{"hostnames": "hosts.txt"}`
+fn named_paths(kind: &str, arguments: &[String]) -> Result<serde_json::Value> {
+    let mut declarations = serde_json::Map::new();
+
+    for argument in arguments {
+        let (name, path) = argument.split_once('=').ok_or(anyhow!(
+            "{} \"{}\" should be given as name=path",
+            kind,
+            argument
+        ))?;
+
+        declarations.insert(name.to_owned(), path.into());
+    }
+
+    Ok(declarations.into())
+}
+
+fn print_generators(generators: &[GeneratorSchema], format: GeneratorsFormat) -> Result<()> {
+    if format == GeneratorsFormat::Json {
+        let schemas: Vec<_> = generators.iter().map(GeneratorSchema::to_json).collect();
+        println!("{}", serde_json::to_string_pretty(&schemas)?);
+
+        return Ok(());
+    }
+
+    let width = generators
+        .iter()
+        .map(|generator| generator.name.len())
+        .max()
+        .unwrap_or(0);
+
+    println!("Available generators:\n");
+
+    for generator in generators {
+        println!("{:width$}  {}", generator.name, generator.description);
+
+        for param in &generator.params {
+            let default = match (&param.default, param.required) {
+                (_, true) => " (required)".to_owned(),
+                (Some(default), _) => format!(" = {}", default),
+                (None, _) => String::new(),
+            };
+
+            println!(
+                "{:width$}    {}: {}{} - {}",
+                "", param.name, param.kind, default, param.description
+            );
+        }
+
+        println!("{:width$}    example: {}", "", generator.example);
+    }
+
+    println!(
+        "\nAvailable locales: {}\n",
+        Locale::ALL.map(|locale| locale.name()).join(", ")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use fakebeat_core::{
+    document_context::DocumentContext, document_renderer::DocumentRendererFactory,
+};
+use serde_json::json;
+
+fn render_benchmark(c: &mut Criterion) {
+    let template = json!({
+        "@timestamp": "{{date(sub_rnd_days=30)}}",
+        "threat": {
+            "indicator": {
+                "type": "url",
+                "first_seen": "{{date()}}",
+                "url": { "full": "http://{{username()}}.com/{{word()}}" },
+                "ip": "{{ipv4()}}",
+                "marking": { "tlp": "RED" }
+            },
+            "feed": { "name": "fakebeat_{{random_value(options='url|domain')}}" }
+        },
+        "event": {
+            "type": "indicator",
+            "category": "threat",
+            "dataset": "ti_*",
+            "kind": "enrichment"
+        }
+    });
+
+    let mut group = c.benchmark_group("render");
+    group.throughput(Throughput::Elements(1));
+
+    let mut renderer = DocumentRendererFactory::create_renderer(Some(1), None);
+    group.bench_function("compile_per_document", |b| {
+        b.iter(|| renderer.render_value(&template).unwrap())
+    });
+
+    let mut renderer = DocumentRendererFactory::create_renderer(Some(1), None);
+    let plan = renderer.compile(&template).unwrap();
+    let document = DocumentContext {
+        seq: 0,
+        global_seq: 0,
+        index: "bench",
+        count: 1,
+    };
+    group.bench_function("compiled_plan", |b| {
+        b.iter(|| renderer.render_plan(&plan, &document).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, render_benchmark);
+criterion_main!(benches);
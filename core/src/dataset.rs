@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Context, Result};
+use rand::{seq::SliceRandom, Rng};
+use serde_json::{Map, Value};
+use tokio::fs::read_to_string;
+
+use crate::dictionary::{split_csv_line, Format};
+
+/// Records loaded from a CSV or JSON file, eg. employees, so that several fields of a document
+/// can come from the same record
+#[derive(Debug)]
+pub struct Dataset {
+    rows: Vec<Value>,
+}
+
+impl Dataset {
+    /// Reads the file of a dataset declaration resolved like a dictionary one, see
+    /// `Dictionary::resolve`
+    pub async fn load(definition: &Value) -> Result<Self> {
+        let path = definition["path"].as_str().unwrap_or_default();
+
+        let content = read_to_string(path)
+            .await
+            .with_context(|| format!("could not read dataset \"{}\"", path))?;
+
+        Self::parse(&content, Format::of(definition))
+            .with_context(|| format!("invalid dataset \"{}\"", path))
+    }
+
+    /// Parses the rows of a CSV file with a header row, or of a JSON array of objects
+    pub fn parse(content: &str, format: Format) -> Result<Self> {
+        let rows = match format {
+            Format::Text => return Err(anyhow!("datasets should be CSV or JSON files")),
+            Format::Csv => parse_csv(content)?,
+            Format::Json => parse_json(content)?,
+        };
+
+        Self::from_rows(rows)
+    }
+
+    /// Dataset of rows generated from templates, eg. an entity population
+    pub fn from_rows(rows: Vec<Value>) -> Result<Self> {
+        if rows.is_empty() {
+            return Err(anyhow!("no rows found"));
+        }
+
+        Ok(Self { rows })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn draw<R: Rng + ?Sized>(&self, rng: &mut R) -> &Value {
+        self.rows.choose(rng).unwrap_or(&Value::Null)
+    }
+}
+
+/// Rows as objects keyed by the header columns, with string values
+fn parse_csv(content: &str) -> Result<Vec<Value>> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+
+    let header = split_csv_line(lines.next().ok_or(anyhow!("missing header row"))?);
+
+    lines
+        .enumerate()
+        .map(|(row, line)| {
+            let fields = split_csv_line(line);
+
+            if fields.len() != header.len() {
+                return Err(anyhow!(
+                    "row {} has {} fields, expected {}",
+                    row + 1,
+                    fields.len(),
+                    header.len()
+                ));
+            }
+
+            Ok(Value::Object(
+                header
+                    .iter()
+                    .cloned()
+                    .zip(fields.into_iter().map(Value::from))
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+fn parse_json(content: &str) -> Result<Vec<Value>> {
+    let items: Vec<Map<String, Value>> = serde_json::from_str(content)
+        .map_err(|err| anyhow!("expected a JSON array of objects: {}", err))?;
+
+    Ok(items.into_iter().map(Value::Object).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Dataset;
+    use crate::dictionary::Format;
+
+    #[test]
+    fn it_parses_csv_and_json_rows() {
+        let csv = Dataset::parse(
+            "id,name,email\n1,\"Doe, Jane\",jane@example.com\n",
+            Format::Csv,
+        )
+        .unwrap();
+
+        assert_eq!(
+            csv.rows,
+            vec![json!({ "id": "1", "name": "Doe, Jane", "email": "jane@example.com" })]
+        );
+
+        let json = Dataset::parse(r#"[{"id": 1, "tags": ["admin"]}]"#, Format::Json).unwrap();
+
+        assert_eq!(json.rows, vec![json!({ "id": 1, "tags": ["admin"] })]);
+
+        assert!(Dataset::parse("id,name\n1\n", Format::Csv).is_err());
+        assert!(Dataset::parse("[\"jane\"]", Format::Json).is_err());
+        assert!(Dataset::parse("jane\n", Format::Text).is_err());
+    }
+}
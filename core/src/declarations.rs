@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use tokio::fs::read_to_string;
+
+use crate::{
+    dataset::Dataset,
+    dictionary::Dictionary,
+    document_renderer::{DocumentRenderer, DocumentRendererFactory},
+    fixture::Fixture,
+    locale::Locale,
+};
+
+/// Settings and run-wide declarations a renderer is created from, so that renderers created from
+/// the same setup render the same documents, eg. one for pipeline samples and one for the run
+#[derive(Debug, Clone, Default)]
+pub struct RendererSetup {
+    pub seed: Option<u64>,
+    pub now: Option<DateTime<Utc>>,
+    pub locale: Option<Locale>,
+    pub run_id: Option<String>,
+    /// Dictionaries declared as `{"name": "path/to/file.txt", ...}`
+    pub dictionaries: Option<serde_json::Value>,
+    /// Datasets declared as `{"name": "path/to/file.csv", ...}`
+    pub datasets: Option<serde_json::Value>,
+    /// Path of a run manifest
+    pub manifest: Option<String>,
+}
+
+impl RendererSetup {
+    pub async fn create_renderer(&self) -> Result<DocumentRenderer> {
+        let mut renderer = DocumentRendererFactory::create_renderer(self.seed, self.now);
+
+        if let Some(locale) = self.locale {
+            renderer.set_default_locale(locale);
+        }
+
+        if let Some(run_id) = &self.run_id {
+            renderer.set_run_id(run_id.clone());
+        }
+
+        if let Some(dictionaries) = &self.dictionaries {
+            load_dictionaries(&mut renderer, dictionaries, Path::new("")).await?;
+        }
+
+        if let Some(datasets) = &self.datasets {
+            load_datasets(&mut renderer, datasets, Path::new("")).await?;
+        }
+
+        if let Some(manifest) = &self.manifest {
+            load_manifest(&mut renderer, manifest).await?;
+        }
+
+        Ok(renderer)
+    }
+}
+
+/// Registers the declarations shared by all the fixtures of a run (eg. value pools, dictionaries,
+/// datasets or entities), found at the top level of a template or a run manifest. Relative file paths are
+/// resolved against `base_dir`
+pub async fn register_declarations(
+    renderer: &mut DocumentRenderer,
+    source: &serde_json::Value,
+    base_dir: &Path,
+) -> Result<()> {
+    if let Some(dictionaries) = source.get("dictionaries") {
+        load_dictionaries(renderer, dictionaries, base_dir).await?;
+    }
+
+    if let Some(datasets) = source.get("datasets") {
+        load_datasets(renderer, datasets, base_dir).await?;
+    }
+
+    if let Some(pools) = source.get("pools") {
+        renderer.register_pools(pools)?;
+    }
+
+    if let Some(entities) = source.get("entities") {
+        renderer.register_entities(entities)?;
+    }
+
+    Ok(())
+}
+
+/// Loads dictionaries declared as `{"name": "path/to/file.txt", ...}`, or with an object
+/// definition, see `Dictionary::resolve`
+pub async fn load_dictionaries(
+    renderer: &mut DocumentRenderer,
+    dictionaries: &serde_json::Value,
+    base_dir: &Path,
+) -> Result<()> {
+    let dictionaries = dictionaries.as_object().ok_or(anyhow!(
+        "dictionaries should be an object of dictionary definitions"
+    ))?;
+
+    for (name, definition) in dictionaries {
+        let definition = Dictionary::resolve(definition, base_dir)
+            .with_context(|| format!("invalid dictionary \"{}\"", name))?;
+
+        let dictionary = Dictionary::load(&definition).await?;
+
+        renderer.register_dictionary(name, &definition, dictionary)?;
+    }
+
+    Ok(())
+}
+
+/// Loads datasets declared like dictionaries, as `{"name": "path/to/file.csv", ...}` or with an
+/// object definition
+pub async fn load_datasets(
+    renderer: &mut DocumentRenderer,
+    datasets: &serde_json::Value,
+    base_dir: &Path,
+) -> Result<()> {
+    let datasets = datasets.as_object().ok_or(anyhow!(
+        "datasets should be an object of dataset definitions"
+    ))?;
+
+    for (name, definition) in datasets {
+        let definition = Dictionary::resolve(definition, base_dir)
+            .with_context(|| format!("invalid dataset \"{}\"", name))?;
+
+        let dataset = Dataset::load(&definition).await?;
+
+        renderer.register_dataset(name, &definition, dataset)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a run manifest and registers its declarations
+pub async fn load_manifest(renderer: &mut DocumentRenderer, path: &str) -> Result<()> {
+    let manifest_file = read_to_string(path).await?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest_file)?;
+
+    register_declarations(renderer, &manifest, parent_dir(path)).await
+}
+
+/// Registers the declarations of every fixture template, so that any fixture can use them
+pub async fn load_fixture_declarations(
+    renderer: &mut DocumentRenderer,
+    fixtures: &[Fixture],
+) -> Result<()> {
+    for fixture in fixtures.iter() {
+        let template_file = read_to_string(&fixture.template).await?;
+        let template: serde_json::Value = serde_json::from_str(&template_file)?;
+
+        register_declarations(renderer, &template, parent_dir(&fixture.template)).await?;
+    }
+
+    Ok(())
+}
+
+fn parent_dir(path: &str) -> &Path {
+    Path::new(path).parent().unwrap_or_else(|| Path::new(""))
+}
@@ -0,0 +1,298 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
+use serde_json::{json, Value};
+use tokio::fs::read_to_string;
+
+/// File format of a dictionary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// One value per line, blank lines and lines starting with `#` are skipped
+    Text,
+    /// Values in a column of a CSV file with a header row
+    Csv,
+    /// Array of values, or of objects to take a field from
+    Json,
+}
+
+impl Format {
+    /// Format of a resolved declaration: its `format`, or the extension of its `path`
+    pub fn of(definition: &Value) -> Self {
+        let path = definition["path"].as_str().unwrap_or_default();
+
+        let format = match definition.get("format").and_then(Value::as_str) {
+            Some(format) => format,
+            None => Path::new(path)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("txt"),
+        };
+
+        match format.to_lowercase().as_str() {
+            "csv" => Format::Csv,
+            "json" => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// List of values loaded from a user file, eg. internal hostnames or known-bad domains
+#[derive(Debug)]
+pub struct Dictionary {
+    values: Vec<Value>,
+    weights: Option<WeightedIndex<f64>>,
+}
+
+impl Dictionary {
+    /// Turns a dictionary declaration, a path or `{"path": ..., "format": ..., "value": ...,
+    /// "weight": ...}`, into the latter with its path resolved against `base_dir`
+    pub fn resolve(definition: &Value, base_dir: &Path) -> Result<Value> {
+        let mut definition = match definition {
+            Value::String(path) => json!({ "path": path }),
+            Value::Object(_) => definition.clone(),
+            _ => return Err(anyhow!("expected a file path or an object with a 'path'")),
+        };
+
+        let path = definition
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or(anyhow!("missing 'path'"))?;
+
+        definition["path"] = base_dir.join(path).to_string_lossy().into();
+
+        Ok(definition)
+    }
+
+    /// Reads the file of a resolved dictionary declaration
+    pub async fn load(definition: &Value) -> Result<Self> {
+        let path = definition["path"].as_str().unwrap_or_default();
+        let format = Format::of(definition);
+
+        let field = |name: &str| -> Result<Option<&str>> {
+            match definition.get(name) {
+                Some(field) => Ok(Some(
+                    field
+                        .as_str()
+                        .ok_or(anyhow!("dictionary '{}' should be a string", name))?,
+                )),
+                None => Ok(None),
+            }
+        };
+
+        let content = read_to_string(path)
+            .await
+            .with_context(|| format!("could not read dictionary \"{}\"", path))?;
+
+        Self::parse(&content, format, field("value")?, field("weight")?)
+            .with_context(|| format!("invalid dictionary \"{}\"", path))
+    }
+
+    /// Parses the content of a dictionary file. `value` and `weight` name the CSV columns or the
+    /// JSON object fields holding the values and their optional weights
+    pub fn parse(
+        content: &str,
+        format: Format,
+        value: Option<&str>,
+        weight: Option<&str>,
+    ) -> Result<Self> {
+        let entries = match format {
+            Format::Text => {
+                if value.is_some() || weight.is_some() {
+                    return Err(anyhow!("text dictionaries have no columns to pick"));
+                }
+
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| (Value::from(line), None))
+                    .collect()
+            }
+            Format::Csv => parse_csv(content, value, weight)?,
+            Format::Json => parse_json(content, value, weight)?,
+        };
+
+        Self::from_entries(entries)
+    }
+
+    fn from_entries(entries: Vec<(Value, Option<f64>)>) -> Result<Self> {
+        if entries.is_empty() {
+            return Err(anyhow!("no values found"));
+        }
+
+        let weighted = entries.iter().any(|(_, weight)| weight.is_some());
+        let (values, weights): (Vec<Value>, Vec<Option<f64>>) = entries.into_iter().unzip();
+
+        let weights = if weighted {
+            Some(
+                WeightedIndex::new(weights.into_iter().map(|weight| weight.unwrap_or(1.0)))
+                    .map_err(|_| {
+                        anyhow!("weights should be non-negative numbers, with at least one above 0")
+                    })?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Self { values, weights })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn draw<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        match &self.weights {
+            Some(weights) => self.values[weights.sample(rng)].clone(),
+            None => self.values.choose(rng).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+fn parse_csv(
+    content: &str,
+    value: Option<&str>,
+    weight: Option<&str>,
+) -> Result<Vec<(Value, Option<f64>)>> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+
+    let header = split_csv_line(lines.next().ok_or(anyhow!("missing header row"))?);
+
+    let column = |name: &str| -> Result<usize> {
+        header
+            .iter()
+            .position(|column| column == name)
+            .ok_or(anyhow!("no \"{}\" column", name))
+    };
+
+    let value_column = match value {
+        Some(value) => column(value)?,
+        None => 0,
+    };
+    let weight_column = weight.map(column).transpose()?;
+
+    lines
+        .enumerate()
+        .map(|(row, line)| {
+            let fields = split_csv_line(line);
+
+            let value = fields
+                .get(value_column)
+                .ok_or(anyhow!("row {} has no value", row + 1))?;
+
+            let weight = match weight_column {
+                Some(column) => Some(
+                    fields
+                        .get(column)
+                        .and_then(|weight| weight.trim().parse::<f64>().ok())
+                        .ok_or(anyhow!("row {} has no numeric weight", row + 1))?,
+                ),
+                None => None,
+            };
+
+            Ok((Value::from(value.as_str()), weight))
+        })
+        .collect()
+}
+
+/// Splits a CSV line on commas, honoring double-quoted fields (quoted line breaks are not
+/// supported)
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(String::new()),
+            (c, _) => fields.last_mut().unwrap().push(c),
+        }
+    }
+
+    fields
+}
+
+fn parse_json(
+    content: &str,
+    value: Option<&str>,
+    weight: Option<&str>,
+) -> Result<Vec<(Value, Option<f64>)>> {
+    let items: Vec<Value> =
+        serde_json::from_str(content).map_err(|err| anyhow!("expected a JSON array: {}", err))?;
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let entry = match value {
+                Some(field) => item.get(field).cloned().ok_or(anyhow!(
+                    "item {} has no \"{}\" field",
+                    i,
+                    field
+                ))?,
+                None => item.clone(),
+            };
+
+            let weight = match weight {
+                Some(field) => Some(item.get(field).and_then(Value::as_f64).ok_or(anyhow!(
+                    "item {} has no numeric \"{}\" field",
+                    i,
+                    field
+                ))?),
+                None => None,
+            };
+
+            Ok((entry, weight))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+
+    use super::{Dictionary, Format};
+
+    #[test]
+    fn it_parses_text_csv_and_json_dictionaries() {
+        let text = Dictionary::parse("# hosts\nweb-1\n\n  db-1 \n", Format::Text, None, None);
+        assert_eq!(text.unwrap().values, vec![json!("web-1"), json!("db-1")]);
+
+        let csv = Dictionary::parse(
+            "id,domain,weight\n1,\"evil, inc.com\",0\n2,bad.com,3\n",
+            Format::Csv,
+            Some("domain"),
+            Some("weight"),
+        )
+        .unwrap();
+
+        assert_eq!(csv.values, vec![json!("evil, inc.com"), json!("bad.com")]);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!((0..100).all(|_| csv.draw(&mut rng) == json!("bad.com")));
+
+        let json = Dictionary::parse(
+            r#"[{"id": "T1059", "weight": 2}, {"id": "T1566"}]"#,
+            Format::Json,
+            Some("id"),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(json.values, vec![json!("T1059"), json!("T1566")]);
+
+        assert!(Dictionary::parse("id\n", Format::Csv, Some("domain"), None).is_err());
+        assert!(Dictionary::parse("[]", Format::Json, None, None).is_err());
+    }
+}
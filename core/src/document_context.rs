@@ -0,0 +1,44 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::json;
+use tera::Context;
+
+/// Variables describing the document being rendered, exposed to the templates as
+/// `doc.seq`, `doc.global_seq`, `doc.timestamp`, `fixture.index`, `fixture.count` and `run.id`
+#[derive(Clone, Debug, Default)]
+pub struct DocumentContext<'a> {
+    /// Sequence number of the document within its fixture, starting at 0
+    pub seq: usize,
+    /// Sequence number of the document across all the fixtures of the run, starting at 0
+    pub global_seq: usize,
+    pub index: &'a str,
+    /// Number of documents generated for the fixture
+    pub count: usize,
+}
+
+impl<'a> DocumentContext<'a> {
+    /// Moves on to the next document of the fixture
+    pub fn advance(&mut self) {
+        self.seq += 1;
+        self.global_seq += 1;
+    }
+
+    pub(crate) fn to_tera_context(&self, run_id: &str, timestamp: DateTime<Utc>) -> Context {
+        let mut context = Context::new();
+
+        context.insert(
+            "doc",
+            &json!({
+                "seq": self.seq,
+                "global_seq": self.global_seq,
+                "timestamp": timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            }),
+        );
+        context.insert(
+            "fixture",
+            &json!({ "index": self.index, "count": self.count }),
+        );
+        context.insert("run", &json!({ "id": run_id }));
+
+        context
+    }
+}
@@ -1,278 +1,2139 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex, RwLock},
+};
 
-use fake::Fake;
-use rand::{distributions::Alphanumeric, seq::SliceRandom, thread_rng, Rng};
+use anyhow::{anyhow, Context as _};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Utc};
 
-use serde_json::{from_value, to_value};
 use tera::{Context, Function, Result, Tera, Value};
 
-const FORMAT_ISO: &str = "%FT%T%z";
+use crate::{
+    dataset::Dataset,
+    dictionary::Dictionary,
+    document_context::DocumentContext,
+    document_template::DocumentTemplate,
+    generator_schema::{GeneratorSchema, ParamType},
+    generators,
+    locale::Locale,
+    pool::Pool,
+    render_plan::{RenderPlan, RepeatCount},
+    scenario::{Gap, Scenario, Step},
+    time_model::TimeModel,
+};
+
+/// Random number generator shared by all the generators, so that a single seed drives the whole run
+pub(crate) type SharedRng = Arc<Mutex<StdRng>>;
+
+/// Datasets or entity populations, keyed by name, sorted so that rows are drawn in a stable order
+pub(crate) type SharedRecords = Arc<RwLock<BTreeMap<String, Dataset>>>;
 
 pub struct DocumentRenderer {
-    generators: HashMap<String, String>,
+    generators: HashMap<String, GeneratorSchema>,
     tera: Tera,
+    rng: SharedRng,
+    /// Pinned "reference now", used instead of the wall clock when set
+    now: Option<DateTime<Utc>>,
+    /// Number of leaf templates compiled so far, used to name them uniquely
+    compiled_templates: usize,
+    /// Identifies the documents produced by this run, available to the templates as `run.id`
+    run_id: String,
+    /// Value pools shared by all the fixtures, keyed by name
+    pools: Arc<RwLock<HashMap<String, Pool>>>,
+    /// Definitions the pools were generated from, to detect conflicting declarations
+    pool_definitions: HashMap<String, Value>,
+    /// Definitions the dictionaries were loaded from, to detect conflicting declarations
+    dictionary_definitions: HashMap<String, Value>,
+    /// Datasets shared by all the fixtures
+    datasets: SharedRecords,
+    /// Definitions the datasets were loaded from, to detect conflicting declarations
+    dataset_definitions: HashMap<String, Value>,
+    /// Entity populations shared by all the fixtures, eg. hosts with stable attributes
+    entities: SharedRecords,
+    /// Definitions the entities were generated from, to detect conflicting declarations
+    entity_definitions: HashMap<String, Value>,
+    /// Locale of the faker-backed generators when neither the call nor the template sets one
+    default_locale: Locale,
+    /// Locale of the faker-backed generators for the document being rendered
+    locale: Arc<RwLock<Locale>>,
+    /// Values rendered so far by the `$unique` directives, keyed by set
+    unique_values: Mutex<HashMap<String, HashSet<String>>>,
 }
 
 impl DocumentRenderer {
+    /// Renders a JSON document template leaf by leaf, so that the output is always valid JSON,
+    /// whatever the generated values contain
     pub fn render(&mut self, template: &str) -> anyhow::Result<String> {
-        let context = Context::default();
+        let template: Value = serde_json::from_str(template)?;
 
-        match self.tera.render_str(template, &context) {
-            Ok(document_string) => Ok(document_string),
-            Err(err) => Err(anyhow::anyhow!(err)),
-        }
+        Ok(self.render_value(&template)?.to_string())
     }
 
-    pub fn get_generators(&self) -> HashMap<String, String> {
-        self.generators.clone()
+    /// Renders every string leaf (and object key) of the `values` definition on its own.
+    ///
+    /// A leaf that consists of a single `{{ ... }}` expression keeps the type of the generated
+    /// value, so `"{{randomint(range=100)}}"` becomes a JSON number and generators returning
+    /// arrays or objects are spliced into the document as-is. Any other leaf renders to a string.
+    ///
+    /// The definition is compiled on every call and rendered as the first document of a fixture,
+    /// use `compile` and `render_plan` to render many documents.
+    pub fn render_value(&mut self, template: &Value) -> anyhow::Result<Value> {
+        let plan = self.compile(template)?;
+
+        self.render_plan(&plan, &DocumentContext::default())
     }
 
-    fn register_generator<F: Function + 'static>(&mut self, name: &str, desc: &str, function: F) {
-        self.tera.register_function(name, function);
-        self.generators.insert(name.to_owned(), desc.to_owned());
+    /// Compiles the `values` definition into a plan that can be rendered for every document,
+    /// so that each string leaf is parsed by tera only once
+    pub fn compile(&mut self, template: &Value) -> anyhow::Result<RenderPlan> {
+        let mut sources = Vec::<(String, String)>::new();
+
+        let plan = self.compile_node(template, "", &mut sources)?;
+
+        self.tera.add_raw_templates(sources)?;
+
+        Ok(plan)
     }
 
-    fn register_generators(&mut self) {
-        self.register_generator(
-            "date",
-            "Generates random date. Optional negative offset can be passed to specify the amount of days to be subtracted, via 'sub_rnd_days' param.",
-            Box::new(move |args: &HashMap<String, Value>| -> Result<Value> {
-                match args.get("sub_rnd_days") {
-                    Some(sub_rnd_days) => match from_value::<i64>(sub_rnd_days.clone()) {
-                        Ok(sub_rnd_days) => {
-                            let mut rng = rand::thread_rng();
-
-                            let random_offset = rng.gen_range(0..sub_rnd_days);
-                            let dt = Utc::now() - Duration::days(random_offset);
-
-                            Ok(to_value(dt.format(FORMAT_ISO).to_string()).unwrap())
-                        }
-                        Err(_) => Err("".into()),
-                    },
-                    None => {
-                        let now = Utc::now().format(FORMAT_ISO);
-                        Ok(to_value(now.to_string()).unwrap())
+    fn compile_node(
+        &mut self,
+        template: &Value,
+        path: &str,
+        sources: &mut Vec<(String, String)>,
+    ) -> anyhow::Result<RenderPlan> {
+        match template {
+            Value::String(leaf) if is_template(leaf) => {
+                let (source, typed) = match typed_expression(leaf) {
+                    Some(expression) => (typed_template(expression), true),
+                    None => (leaf.to_owned(), false),
+                };
+
+                Ok(self.compile_leaf(source, typed, path, sources))
+            }
+            Value::Array(items) => {
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.compile_node(item, &item_path(path, i), sources))
+                    .collect::<anyhow::Result<Vec<RenderPlan>>>()?;
+
+                if items.iter().all(RenderPlan::is_literal) {
+                    return Ok(RenderPlan::Literal(template.clone()));
+                }
+
+                Ok(RenderPlan::Array(items))
+            }
+            Value::Object(fields) if directive(fields, OPTIONAL).is_some() => {
+                let (probability, null_probability, value) =
+                    optional_definition(directive(fields, OPTIONAL).unwrap(), path)?;
+
+                Ok(RenderPlan::Optional {
+                    probability,
+                    null_probability,
+                    value: Box::new(self.compile_node(value, path, sources)?),
+                })
+            }
+            Value::Object(fields) if directive(fields, UNIQUE).is_some() => {
+                let (set, attempts, value) =
+                    unique_definition(directive(fields, UNIQUE).unwrap(), path)?;
+
+                Ok(RenderPlan::Unique {
+                    set,
+                    attempts,
+                    value: Box::new(self.compile_node(value, path, sources)?),
+                    path: path.to_owned(),
+                })
+            }
+            Value::Object(fields) if directive(fields, REPEAT).is_some() => {
+                let (count, value) = repeat_definition(directive(fields, REPEAT).unwrap(), path)?;
+
+                let count = match count {
+                    Count::Between(min, max) => RepeatCount::Between(min, max),
+                    Count::Rendered(count) => {
+                        RepeatCount::Rendered(Box::new(self.compile_node(count, path, sources)?))
                     }
+                };
+
+                Ok(RenderPlan::Repeat {
+                    count,
+                    value: Box::new(self.compile_node(value, &item_path(path, 0), sources)?),
+                    path: path.to_owned(),
+                })
+            }
+            Value::Object(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(key, value)| {
+                        let path = field_path(path, key);
+
+                        let key = if is_template(key) {
+                            self.compile_leaf(key.to_owned(), false, &path, sources)
+                        } else {
+                            RenderPlan::Literal(Value::String(key.to_owned()))
+                        };
+
+                        Ok((key, self.compile_node(value, &path, sources)?))
+                    })
+                    .collect::<anyhow::Result<Vec<(RenderPlan, RenderPlan)>>>()?;
+
+                if fields
+                    .iter()
+                    .all(|(key, value)| key.is_literal() && value.is_literal())
+                {
+                    return Ok(RenderPlan::Literal(template.clone()));
                 }
-            }),
-        );
 
-        self.register_generator(
-            "now",
-            "Current date",
-            Box::new(move |_: &HashMap<String, Value>| -> Result<Value> {
-                let now = Utc::now().format(FORMAT_ISO);
-                Ok(to_value(now.to_string()).unwrap_or_default())
-            }),
-        );
+                Ok(RenderPlan::Object(fields))
+            }
+            other => Ok(RenderPlan::Literal(other.clone())),
+        }
+    }
 
-        self.register_generator(
-            "hash",
-            "16-character long alpha-num hash",
-            Box::new(move |_: &HashMap<String, Value>| -> Result<Value> {
-                let value = thread_rng()
-                    .sample_iter(&Alphanumeric)
-                    .take(16)
-                    .map(char::from)
-                    .collect::<String>();
+    fn compile_leaf(
+        &mut self,
+        source: String,
+        typed: bool,
+        path: &str,
+        sources: &mut Vec<(String, String)>,
+    ) -> RenderPlan {
+        let name = format!("__leaf_{}", self.compiled_templates);
+        self.compiled_templates += 1;
 
-                let value = to_value(value).unwrap_or_default();
+        sources.push((name.clone(), source));
 
-                Ok(value)
-            }),
+        RenderPlan::Template {
+            name,
+            typed,
+            path: path.to_owned(),
+        }
+    }
+
+    /// Compiles a fixture template file: its `values` definition and optional `time` model
+    pub fn compile_template(&mut self, template: &Value) -> anyhow::Result<DocumentTemplate> {
+        let values = template
+            .get("values")
+            .ok_or(anyhow!("missing values definition, check your template"))?;
+
+        let (time, locale) = self.template_settings(template)?;
+
+        Ok(DocumentTemplate {
+            values: self.compile(values)?,
+            time,
+            locale,
+        })
+    }
+
+    /// Compiles a fixture template file declaring a `scenario` instead of `values`, eg.
+    /// `{"scenario": {"state": {...}, "steps": [{"values": {...}}, {"gap": "5s", "values": {...}}]}}`
+    pub fn compile_scenario(&mut self, template: &Value) -> anyhow::Result<Scenario> {
+        let scenario = template
+            .get("scenario")
+            .ok_or(anyhow!("missing scenario definition, check your template"))?;
+
+        let state = match scenario.get("state") {
+            Some(state) => self.compile(state)?,
+            None => RenderPlan::Literal(Value::Object(serde_json::Map::new())),
+        };
+
+        let steps = scenario
+            .get("steps")
+            .and_then(Value::as_array)
+            .filter(|steps| !steps.is_empty())
+            .ok_or(anyhow!("scenario is missing its 'steps' list"))?
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let values = step
+                    .get("values")
+                    .ok_or(anyhow!("scenario step {} is missing its values", i))?;
+
+                let gap = match step.get("gap") {
+                    Some(gap) => Gap::from_definition(gap)
+                        .with_context(|| format!("invalid gap of scenario step {}", i))?,
+                    None => Gap::default(),
+                };
+
+                Ok(Step {
+                    gap,
+                    values: self.compile(values)?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<Step>>>()?;
+
+        let (time, locale) = self.template_settings(template)?;
+
+        Ok(Scenario {
+            state,
+            steps,
+            time,
+            locale,
+        })
+    }
+
+    /// Fixture-level settings of a template: its `time` model and `locale`
+    fn template_settings(
+        &self,
+        template: &Value,
+    ) -> anyhow::Result<(Option<TimeModel>, Option<Locale>)> {
+        let locale = match template.get("locale") {
+            Some(locale) => Some(
+                locale
+                    .as_str()
+                    .ok_or(anyhow!("template locale should be a string"))?
+                    .parse::<Locale>()?,
+            ),
+            None => None,
+        };
+
+        let time = match template.get("time") {
+            Some(definition) => Some(
+                TimeModel::from_definition(definition, self.now.unwrap_or_else(Utc::now))
+                    .context("invalid time definition")?,
+            ),
+            None => None,
+        };
+
+        Ok((time, locale))
+    }
+
+    /// Renders a single document of a fixture, `doc.timestamp` being drawn from its time model, or
+    /// now without one
+    pub fn render_document(
+        &self,
+        template: &DocumentTemplate,
+        document: &DocumentContext,
+    ) -> anyhow::Result<Value> {
+        let timestamp = match &template.time {
+            Some(time) => time.sample(&mut *self.rng.lock().unwrap(), document.seq, document.count),
+            None => self.now.unwrap_or_else(Utc::now),
+        };
+
+        let mut context = document.to_tera_context(&self.run_id, timestamp);
+        context.insert("datasets", &self.draw_rows(&self.datasets));
+        context.insert("entities", &self.draw_rows(&self.entities));
+
+        *self.locale.write().unwrap() = template.locale.unwrap_or(self.default_locale);
+
+        self.evaluate(&template.values, &context)
+    }
+
+    /// Renders the documents of one sequence of a scenario, one per step, `document` describing
+    /// the first of them.
+    ///
+    /// All the steps share the dataset rows and entities of the sequence, along with the `state`,
+    /// and are exposed `scenario.seq` (number of the sequence) and `scenario.step`.
+    /// `doc.timestamp` starts at a time drawn from the time model, or now, and moves forward by
+    /// the gap of every step.
+    pub fn render_sequence(
+        &self,
+        scenario: &Scenario,
+        document: &mut DocumentContext,
+    ) -> anyhow::Result<Vec<Value>> {
+        let steps = scenario.steps.len();
+        let sequence = document.seq / steps;
+
+        let mut timestamp = match &scenario.time {
+            Some(time) => time.sample(
+                &mut *self.rng.lock().unwrap(),
+                sequence,
+                document.count / steps,
+            ),
+            None => self.now.unwrap_or_else(Utc::now),
+        };
+
+        *self.locale.write().unwrap() = scenario.locale.unwrap_or(self.default_locale);
+
+        let datasets = self.draw_rows(&self.datasets);
+        let entities = self.draw_rows(&self.entities);
+
+        let context = |document: &DocumentContext, timestamp: DateTime<Utc>| {
+            let mut context = document.to_tera_context(&self.run_id, timestamp);
+            context.insert("datasets", &datasets);
+            context.insert("entities", &entities);
+
+            context
+        };
+
+        let state = self
+            .evaluate(&scenario.state, &context(document, timestamp))
+            .context("could not render the scenario state")?;
+
+        let mut documents = Vec::with_capacity(steps);
+
+        for (i, step) in scenario.steps.iter().enumerate() {
+            timestamp += step.gap.sample(&mut *self.rng.lock().unwrap());
+
+            let mut context = context(document, timestamp);
+            context.insert("state", &state);
+            context.insert(
+                "scenario",
+                &serde_json::json!({ "seq": sequence, "step": i }),
+            );
+
+            documents.push(
+                self.evaluate(&step.values, &context)
+                    .with_context(|| format!("could not render scenario step {}", i))?,
+            );
+
+            document.advance();
+        }
+
+        Ok(documents)
+    }
+
+    /// Renders a single document from a compiled plan
+    pub fn render_plan(
+        &self,
+        plan: &RenderPlan,
+        document: &DocumentContext,
+    ) -> anyhow::Result<Value> {
+        let mut context = document.to_tera_context(&self.run_id, self.now.unwrap_or_else(Utc::now));
+        context.insert("datasets", &self.draw_rows(&self.datasets));
+        context.insert("entities", &self.draw_rows(&self.entities));
+
+        *self.locale.write().unwrap() = self.default_locale;
+
+        self.evaluate(plan, &context)
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn set_run_id(&mut self, run_id: String) {
+        self.run_id = run_id;
+    }
+
+    /// Generates the value pools declared in a template or manifest, eg.
+    /// `{"urls": {"size": 500, "value": "http://{{ word() }}.com"}}`.
+    ///
+    /// Besides `size` members, every pool gets `decoys` values (`size` by default) rendered from
+    /// the same template, which never collide with the members.
+    pub fn register_pools(&mut self, pools: &Value) -> anyhow::Result<()> {
+        let pools = pools
+            .as_object()
+            .ok_or(anyhow!("pools should be an object of pool definitions"))?;
+
+        for (name, definition) in pools {
+            if let Some(existing) = self.pool_definitions.get(name) {
+                if existing == definition {
+                    continue;
+                }
+
+                return Err(anyhow!(
+                    "pool \"{}\" is declared more than once with different definitions",
+                    name
+                ));
+            }
+
+            let size = definition
+                .get("size")
+                .and_then(Value::as_u64)
+                .ok_or(anyhow!("pool \"{}\" is missing a numeric 'size'", name))?
+                as usize;
+
+            let decoys = match definition.get("decoys") {
+                Some(decoys) => decoys
+                    .as_u64()
+                    .ok_or(anyhow!("pool \"{}\" has a non-numeric 'decoys'", name))?
+                    as usize,
+                None => size,
+            };
+
+            let template = definition
+                .get("value")
+                .ok_or(anyhow!("pool \"{}\" is missing its 'value' template", name))?;
+
+            let plan = self.compile(template)?;
+
+            let mut seen = HashSet::<String>::new();
+            let members = self.generate_distinct(name, &plan, size, &mut seen)?;
+            let decoys = self.generate_distinct(name, &plan, decoys, &mut seen)?;
+
+            self.pools
+                .write()
+                .unwrap()
+                .insert(name.to_owned(), Pool { members, decoys });
+
+            self.pool_definitions
+                .insert(name.to_owned(), definition.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Registers a generator named `name` drawing from a dictionary loaded from `definition`,
+    /// see `Dictionary::resolve`
+    pub fn register_dictionary(
+        &mut self,
+        name: &str,
+        definition: &Value,
+        dictionary: Dictionary,
+    ) -> anyhow::Result<()> {
+        if let Some(existing) = self.dictionary_definitions.get(name) {
+            if existing == definition {
+                return Ok(());
+            }
+
+            return Err(anyhow!(
+                "dictionary \"{}\" is declared more than once with different definitions",
+                name
+            ));
+        }
+
+        if self.generators.contains_key(name) {
+            return Err(anyhow!(
+                "dictionary \"{}\" has the same name as a generator",
+                name
+            ));
+        }
+
+        let schema = GeneratorSchema::new(
+            name,
+            &format!(
+                "Random value of the {} dictionary ({} values)",
+                definition["path"].as_str().unwrap_or_default(),
+                dictionary.len()
+            ),
         );
 
-        self.register_generator(
-            "random_value",
-            "Get random value from set configured with the 'options' parameter, eg. options='a|b|c'",
-            move |args: &HashMap<String, Value>| -> Result<Value> {
-                let mut rng = thread_rng();
-
-                match args.get("options") {
-                    Some(value) => {
-                        if let Some(options) = value.as_str() {
-                            let options: Vec<&str> = options.split("|").collect();
-                            let random_value = options.choose(&mut rng);
-
-                            if let Some(random_value) = random_value {
-                                Ok(random_value.to_owned().into())
-                            } else {
-                                Ok("".into())
-                            }
+        let rng = Arc::clone(&self.rng);
+        self.register_generator(schema, move |_: &HashMap<String, Value>| -> Result<Value> {
+            Ok(dictionary.draw(&mut *rng.lock().unwrap()))
+        });
+
+        self.dictionary_definitions
+            .insert(name.to_owned(), definition.clone());
+
+        Ok(())
+    }
+
+    /// Registers a dataset loaded from `definition`, whose rows are exposed to the templates as
+    /// `datasets.<name>`
+    pub fn register_dataset(
+        &mut self,
+        name: &str,
+        definition: &Value,
+        dataset: Dataset,
+    ) -> anyhow::Result<()> {
+        if let Some(existing) = self.dataset_definitions.get(name) {
+            if existing == definition {
+                return Ok(());
+            }
+
+            return Err(anyhow!(
+                "dataset \"{}\" is declared more than once with different definitions",
+                name
+            ));
+        }
+
+        self.datasets
+            .write()
+            .unwrap()
+            .insert(name.to_owned(), dataset);
+
+        self.dataset_definitions
+            .insert(name.to_owned(), definition.clone());
+
+        Ok(())
+    }
+
+    /// Generates the entity populations declared in a template or manifest, eg.
+    /// `{"host": {"size": 50, "attributes": {"name": "host-{{ doc.seq }}", "ip": "{{ ipv4() }}"}}}`.
+    ///
+    /// The attributes of an entity are rendered together, like the fields of a document, so
+    /// they can share a dataset row. `doc.seq` numbers the entities of a population.
+    pub fn register_entities(&mut self, entities: &Value) -> anyhow::Result<()> {
+        let entities = entities.as_object().ok_or(anyhow!(
+            "entities should be an object of entity definitions"
+        ))?;
+
+        for (name, definition) in entities {
+            if let Some(existing) = self.entity_definitions.get(name) {
+                if existing == definition {
+                    continue;
+                }
+
+                return Err(anyhow!(
+                    "entity \"{}\" is declared more than once with different definitions",
+                    name
+                ));
+            }
+
+            let size = definition
+                .get("size")
+                .and_then(Value::as_u64)
+                .filter(|size| *size > 0)
+                .ok_or(anyhow!("entity \"{}\" is missing a positive 'size'", name))?
+                as usize;
+
+            let attributes = definition
+                .get("attributes")
+                .filter(|attributes| attributes.is_object())
+                .ok_or(anyhow!(
+                    "entity \"{}\" is missing its 'attributes' object",
+                    name
+                ))?;
+
+            let plan = self.compile(attributes)?;
+
+            let mut document = DocumentContext {
+                seq: 0,
+                global_seq: 0,
+                index: name,
+                count: size,
+            };
+
+            let mut population = Vec::with_capacity(size);
+
+            for _ in 0..size {
+                population.push(
+                    self.render_plan(&plan, &document)
+                        .with_context(|| format!("could not generate entity \"{}\"", name))?,
+                );
+
+                document.advance();
+            }
+
+            self.entities
+                .write()
+                .unwrap()
+                .insert(name.to_owned(), Dataset::from_rows(population)?);
+
+            self.entity_definitions
+                .insert(name.to_owned(), definition.clone());
+        }
+
+        Ok(())
+    }
+
+    /// One random row of every dataset or population, shared by all the fields of a document
+    fn draw_rows(&self, records: &SharedRecords) -> Value {
+        let records = records.read().unwrap();
+        let mut rng = self.rng.lock().unwrap();
+
+        Value::Object(
+            records
+                .iter()
+                .map(|(name, records)| (name.clone(), records.draw(&mut *rng).clone()))
+                .collect(),
+        )
+    }
+
+    fn generate_distinct(
+        &self,
+        name: &str,
+        plan: &RenderPlan,
+        count: usize,
+        seen: &mut HashSet<String>,
+    ) -> anyhow::Result<Vec<Value>> {
+        let mut document = DocumentContext {
+            seq: 0,
+            global_seq: 0,
+            index: name,
+            count,
+        };
+
+        let max_attempts = count * 100 + 100;
+        let mut values = Vec::with_capacity(count);
+
+        for _ in 0..max_attempts {
+            if values.len() == count {
+                break;
+            }
+
+            let value = self
+                .render_plan(plan, &document)
+                .with_context(|| format!("could not generate pool \"{}\"", name))?;
+
+            if seen.insert(value.to_string()) {
+                values.push(value);
+            }
+
+            document.advance();
+        }
+
+        if values.len() < count {
+            return Err(anyhow!(
+                "could not generate {} distinct values for pool \"{}\", its value template is not random enough",
+                count,
+                name
+            ));
+        }
+
+        Ok(values)
+    }
+
+    fn evaluate(&self, plan: &RenderPlan, context: &Context) -> anyhow::Result<Value> {
+        match plan {
+            RenderPlan::Literal(value) => Ok(value.clone()),
+            RenderPlan::Template { name, typed, path } => {
+                let rendered = self
+                    .tera
+                    .render(name, context)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|rendered| {
+                        if *typed {
+                            Ok(serde_json::from_str(&rendered)?)
                         } else {
-                            Ok("".into())
+                            Ok(Value::String(rendered))
                         }
+                    });
+
+                rendered.with_context(|| format!("could not render \"{}\"", path))
+            }
+            RenderPlan::Array(items) => Ok(Value::Array(
+                items
+                    .iter()
+                    .filter_map(|item| self.evaluate_present(item, context).transpose())
+                    .collect::<anyhow::Result<Vec<Value>>>()?,
+            )),
+            RenderPlan::Object(fields) => {
+                let mut rendered = serde_json::Map::with_capacity(fields.len());
+
+                for (key, value) in fields {
+                    let key = match self.evaluate(key, context)? {
+                        Value::String(key) => key,
+                        other => other.to_string(),
+                    };
+
+                    if let Some(value) = self.evaluate_present(value, context)? {
+                        rendered.insert(key, value);
                     }
-                    None => Ok("".into()),
                 }
-            },
-        );
 
-        self.register_generator(
-            "chance",
-            "Roll a dice within range, if 0 is rolled then return first option, else 2nd",
-            move |args: &HashMap<String, Value>| -> Result<Value> {
-                let mut rng = thread_rng();
-                let range = args.get("range").unwrap().as_u64().unwrap();
-                let options = args.get("options").unwrap().as_str().unwrap();
-                let chance = rng.gen_range(0..range);
-                let options: Vec<&str> = options.split("|").collect();
-
-                if chance == 0 {
-                    Ok(options.get(0).unwrap().to_owned().into())
-                } else {
-                    Ok(options.get(1).unwrap().to_owned().into())
+                Ok(Value::Object(rendered))
+            }
+            RenderPlan::Repeat { count, value, path } => {
+                let count = match count {
+                    RepeatCount::Between(min, max) => {
+                        self.rng.lock().unwrap().gen_range(*min..=*max)
+                    }
+                    RepeatCount::Rendered(count) => {
+                        repeat_count(&self.evaluate(count, context)?, path)?
+                    }
+                };
+
+                Ok(Value::Array(
+                    (0..count)
+                        .filter_map(|_| self.evaluate_present(value, context).transpose())
+                        .collect::<anyhow::Result<Vec<Value>>>()?,
+                ))
+            }
+            RenderPlan::Optional { .. } => {
+                Ok(self.evaluate_present(plan, context)?.unwrap_or(Value::Null))
+            }
+            RenderPlan::Unique {
+                set,
+                attempts,
+                value,
+                path,
+            } => {
+                for _ in 0..*attempts {
+                    let candidate = self.evaluate(value, context)?;
+
+                    if self.is_unique(set, &candidate) {
+                        return Ok(candidate);
+                    }
                 }
-            },
+
+                Err(no_unique_value(path, *attempts))
+            }
+        }
+    }
+
+    /// Records a value rendered by a `$unique` directive, returns whether it is new to its set
+    fn is_unique(&self, set: &str, value: &Value) -> bool {
+        let mut unique_values = self.unique_values.lock().unwrap();
+
+        match unique_values.get_mut(set) {
+            Some(values) => values.insert(value.to_string()),
+            None => {
+                unique_values.insert(set.to_owned(), HashSet::from([value.to_string()]));
+                true
+            }
+        }
+    }
+
+    /// Evaluates a plan, or returns `None` when it is an optional value left out of the document
+    fn evaluate_present(
+        &self,
+        plan: &RenderPlan,
+        context: &Context,
+    ) -> anyhow::Result<Option<Value>> {
+        let (probability, null_probability, value) = match plan {
+            RenderPlan::Optional {
+                probability,
+                null_probability,
+                value,
+            } => (*probability, *null_probability, value),
+            _ => return Ok(Some(self.evaluate(plan, context)?)),
+        };
+
+        let presence = presence(
+            &mut *self.rng.lock().unwrap(),
+            probability,
+            null_probability,
         );
+
+        match presence {
+            Presence::Value => Ok(Some(self.evaluate(value, context)?)),
+            Presence::Null => Ok(Some(Value::Null)),
+            Presence::Missing => Ok(None),
+        }
+    }
+
+    /// Schemas of all the registered generators, sorted by name
+    pub fn get_generators(&self) -> Vec<GeneratorSchema> {
+        let mut generators: Vec<GeneratorSchema> = self.generators.values().cloned().collect();
+        generators.sort_by(|a, b| a.name.cmp(&b.name));
+
+        generators
+    }
+
+    pub(crate) fn rng(&self) -> SharedRng {
+        Arc::clone(&self.rng)
+    }
+
+    pub(crate) fn reference_now(&self) -> Option<DateTime<Utc>> {
+        self.now
+    }
+
+    pub(crate) fn active_locale(&self) -> Arc<RwLock<Locale>> {
+        Arc::clone(&self.locale)
+    }
+
+    pub(crate) fn datasets(&self) -> SharedRecords {
+        Arc::clone(&self.datasets)
+    }
+
+    pub(crate) fn entities(&self) -> SharedRecords {
+        Arc::clone(&self.entities)
+    }
+
+    /// Sets the locale of the faker-backed generators for templates which do not declare one
+    pub fn set_default_locale(&mut self, locale: Locale) {
+        self.default_locale = locale;
+    }
+
+    pub(crate) fn register_generator<F: Function + 'static>(
+        &mut self,
+        schema: GeneratorSchema,
+        function: F,
+    ) {
+        self.tera.register_function(&schema.name, function);
+        self.generators.insert(schema.name.clone(), schema);
+    }
+
+    fn register_generators(&mut self) {
+        generators::date::register(self);
+        generators::sequence::register(self);
+        generators::choice::register(self);
+        generators::numbers::register(self);
+        generators::ecs::register(self);
+        generators::geo::register(self);
+
+        let rng = Arc::clone(&self.rng);
+        let pools = Arc::clone(&self.pools);
         self.register_generator(
-            "randomint",
-            "Roll a dice within a range",
+            GeneratorSchema::new("pool", "Value from a pool declared under 'pools'")
+                .required("name", ParamType::String, "Name of the pool")
+                .optional(
+                    "index",
+                    ParamType::Integer,
+                    "Picks the values in order, eg. doc.seq, instead of randomly",
+                )
+                .optional(
+                    "overlap",
+                    ParamType::Number,
+                    "Fraction (0-1) of the values taken from the pool, the others never match it",
+                )
+                .example("{{ pool(name='urls', overlap=0.1, index=doc.seq) }}"),
             move |args: &HashMap<String, Value>| -> Result<Value> {
-                let mut rng = thread_rng();
-                let range = args
-                    .get("range")
-                    .unwrap_or(&Value::String("0".to_owned()))
-                    .as_u64()
-                    .unwrap();
-                let range = rng.gen_range(0..range);
+                let name = args
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or("pool: missing 'name' parameter")?;
 
-                Ok(range.to_string().into())
-            },
-        );
+                let index = match args.get("index") {
+                    Some(index) => Some(
+                        index
+                            .as_u64()
+                            .ok_or("pool: 'index' must be a non-negative integer")?,
+                    ),
+                    None => None,
+                };
 
-        macro_rules! register_faker_generators {
-            (    $($i:ident: $p:path), *) => {
-                    $(
-                        self.register_generator(stringify!($i), stringify!($p), Box::new(move |_: &HashMap<String, Value>| -> Result<Value> {
-                            let value = to_value($p().fake::<String>()).unwrap_or_default();
-                            Ok(value)
-                        }));
-                    )*
-                }
-            }
+                let overlap = match args.get("overlap") {
+                    Some(overlap) => match overlap.as_f64() {
+                        Some(overlap) if (0.0..=1.0).contains(&overlap) => Some(overlap),
+                        _ => return Err("pool: 'overlap' must be a number between 0 and 1".into()),
+                    },
+                    None => None,
+                };
 
-        register_faker_generators!(
-            // Numbers
-            digit: fake::faker::number::en::Digit,
-            // Internet
-            username: fake::faker::internet::en::Username,
-            domainsuffix: fake::faker::internet::en::DomainSuffix,
-            ipv4: fake::faker::internet::en::IPv4,
-            ipv6: fake::faker::internet::en::IPv6,
-            ip: fake::faker::internet::en::IP,
-            macaddress: fake::faker::internet::en::MACAddress,
-            freeemail: fake::faker::internet::en::FreeEmail,
-            safeemail: fake::faker::internet::en::SafeEmail,
-            freeemailprovider: fake::faker::internet::en::FreeEmailProvider,
-            // Lorem ipsum
-            word: fake::faker::lorem::en::Word,
-            // Name
-            firstname: fake::faker::name::en::FirstName,
-            lastname: fake::faker::name::en::LastName,
-            title: fake::faker::name::en::Title,
-            suffix: fake::faker::name::en::Suffix,
-            name: fake::faker::name::en::Name,
-            namewithtitle: fake::faker::name::en::NameWithTitle,
-            //Filesystem
-            filepath: fake::faker::filesystem::en::FilePath,
-            filename: fake::faker::filesystem::en::FileName,
-            fileextension: fake::faker::filesystem::en::FileExtension,
-            dirpath: fake::faker::filesystem::en::DirPath,
-            // Company
-            companysuffix: fake::faker::company::en::CompanySuffix,
-            companyname: fake::faker::company::en::CompanyName,
-            buzzword: fake::faker::company::en::Buzzword,
-            buzzwordmiddle: fake::faker::company::en::BuzzwordMiddle,
-            buzzwordtail: fake::faker::company::en::BuzzwordTail,
-            catchphase: fake::faker::company::en::CatchPhase,
-            bsverb: fake::faker::company::en::BsVerb,
-            bsadj: fake::faker::company::en::BsAdj,
-            bsnoun: fake::faker::company::en::BsNoun,
-            bs: fake::faker::company::en::Bs,
-            profession: fake::faker::company::en::Profession,
-            industry: fake::faker::company::en::Industry,
-            // Address
-            cityprefix: fake::faker::address::en::CityPrefix,
-            citysuffix: fake::faker::address::en::CitySuffix,
-            cityname: fake::faker::address::en::CityName,
-            countryname: fake::faker::address::en::CountryName,
-            countrycode: fake::faker::address::en::CountryCode,
-            streetsuffix: fake::faker::address::en::StreetSuffix,
-            streetname: fake::faker::address::en::StreetName,
-            timezone: fake::faker::address::en::TimeZone,
-            statename: fake::faker::address::en::StateName,
-            stateabbr: fake::faker::address::en::StateAbbr,
-            secondaryaddresstype: fake::faker::address::en::SecondaryAddressType,
-            secondaryaddress: fake::faker::address::en::SecondaryAddress,
-            zipcode: fake::faker::address::en::ZipCode,
-            postcode: fake::faker::address::en::PostCode,
-            buildingnumber: fake::faker::address::en::BuildingNumber,
-            latitude: fake::faker::address::en::Latitude,
-            longitude: fake::faker::address::en::Longitude
+                let pools = pools.read().unwrap();
+                let pool = pools
+                    .get(name)
+                    .ok_or_else(|| format!("pool: unknown pool '{}'", name))?;
+
+                let value = pool
+                    .draw(&mut *rng.lock().unwrap(), index, overlap)
+                    .ok_or_else(|| format!("pool: pool '{}' is empty", name))?;
+
+                Ok(value)
+            },
         );
+
+        generators::records::register(self);
+        generators::faker::register(self);
+        generators::network::register(self);
     }
 
-    fn new() -> Self {
+    fn new(seed: Option<u64>, now: Option<DateTime<Utc>>) -> Self {
         let tera = Tera::default();
 
-        let generators = HashMap::<String, String>::new();
+        let generators = HashMap::<String, GeneratorSchema>::new();
+
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
 
-        return Self { tera, generators };
+        let run_id = format!("{:016x}", rng.gen::<u64>());
+
+        return Self {
+            tera,
+            generators,
+            rng: Arc::new(Mutex::new(rng)),
+            now,
+            compiled_templates: 0,
+            run_id,
+            pools: Arc::new(RwLock::new(HashMap::new())),
+            pool_definitions: HashMap::new(),
+            dictionary_definitions: HashMap::new(),
+            datasets: Arc::new(RwLock::new(BTreeMap::new())),
+            dataset_definitions: HashMap::new(),
+            entities: Arc::new(RwLock::new(BTreeMap::new())),
+            entity_definitions: HashMap::new(),
+            unique_values: Mutex::new(HashMap::new()),
+            default_locale: Locale::default(),
+            locale: Arc::new(RwLock::new(Locale::default())),
+        };
     }
 }
 
-pub struct DocumentRendererFactory {}
+fn field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
 
-impl DocumentRendererFactory {
-    pub fn create_renderer() -> DocumentRenderer {
-        let mut document_renderer = DocumentRenderer::new();
+fn item_path(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
 
-        document_renderer.register_generators();
+/// Key of the directive repeating a value into an array, eg.
+/// `{"$repeat": {"min": 1, "max": 3, "value": "{{ ipv4() }}"}}`
+const REPEAT: &str = "$repeat";
 
-        document_renderer
+/// Key of the directive making a value optional, eg.
+/// `{"$optional": {"probability": 0.6, "null_probability": 0.1, "value": "{{ username() }}"}}`
+const OPTIONAL: &str = "$optional";
+
+/// Key of the directive rendering a value again until it is unique within the run, eg.
+/// `{"$unique": {"value": "{{ randomint(max=999999) }}", "set": "order_numbers"}}`
+const UNIQUE: &str = "$unique";
+
+/// Default number of renderings of a `$unique` value before giving up
+const UNIQUE_ATTEMPTS: usize = 100;
+
+/// Definition of a directive, an object with the directive as its only key
+fn directive<'a>(fields: &'a serde_json::Map<String, Value>, name: &str) -> Option<&'a Value> {
+    match fields.len() {
+        1 => fields.get(name),
+        _ => None,
     }
 }
 
-#[cfg(test)]
-mod tests {
-    const FORMAT_ISO: &str = "%FT%T%z";
+/// Number of items of a `$repeat` directive, as declared
+enum Count<'a> {
+    Between(u64, u64),
+    Rendered(&'a Value),
+}
 
-    use chrono::Utc;
+/// Reads `{"min": .., "max": .., "value": ..}` or `{"count": .., "value": ..}`, where `count` is
+/// a number or a template like `"{{ poisson(lambda=3) }}"`
+fn repeat_definition<'a>(
+    definition: &'a Value,
+    path: &str,
+) -> anyhow::Result<(Count<'a>, &'a Value)> {
+    let invalid = |reason: &str| anyhow!("invalid {} at \"{}\": {}", REPEAT, path, reason);
 
-    use crate::document_renderer::DocumentRendererFactory;
+    let value = definition
+        .get("value")
+        .ok_or_else(|| invalid("missing 'value'"))?;
 
-    #[test]
-    fn it_replaces_the_generators_with_values() {
-        let mut renderer = DocumentRendererFactory::create_renderer();
+    let bound = |name: &str| -> anyhow::Result<Option<u64>> {
+        match definition.get(name) {
+            Some(bound) => Ok(Some(bound.as_u64().ok_or_else(|| {
+                invalid(&format!("'{}' must be a non-negative integer", name))
+            })?)),
+            None => Ok(None),
+        }
+    };
 
-        let result = renderer
-            .render(
-                r#"{
-            "values": {
-              "@timestamp": "{{date()}}",
-              "file.hash.md5": "{{hash()}}"
-            },
-            "index": {
-              "mappings": {
-                "properties": {
-                  "file.hash.md5": { "type": "keyword" },
-                  "@timestamp": { "type": "date" }
-                }
-              }
-            }
-          }
-        "#,
-            )
-            .unwrap();
+    let count = match (definition.get("count"), bound("min")?, bound("max")?) {
+        (Some(count), None, None) => Count::Rendered(count),
+        (None, min, Some(max)) if min.unwrap_or(0) <= max => Count::Between(min.unwrap_or(0), max),
+        (None, _, Some(_)) => return Err(invalid("'min' must not be greater than 'max'")),
+        (None, _, None) => return Err(invalid("expected either 'count' or 'max'")),
+        (Some(_), _, _) => return Err(invalid("use either 'count' or 'min' and 'max'")),
+    };
 
-        assert_eq!(result.contains("date()"), false);
+    Ok((count, value))
+}
+
+/// Reads `{"probability": .., "null_probability": .., "value": ..}`. Without `probability` the
+/// value is always present, unless replaced by `null`.
+fn optional_definition<'a>(
+    definition: &'a Value,
+    path: &str,
+) -> anyhow::Result<(f64, f64, &'a Value)> {
+    let invalid = |reason: &str| anyhow!("invalid {} at \"{}\": {}", OPTIONAL, path, reason);
+
+    let value = definition
+        .get("value")
+        .ok_or_else(|| invalid("missing 'value'"))?;
+
+    let probability = |name: &str| -> anyhow::Result<Option<f64>> {
+        match definition.get(name) {
+            Some(probability) => Ok(Some(
+                probability
+                    .as_f64()
+                    .filter(|probability| (0.0..=1.0).contains(probability))
+                    .ok_or_else(|| invalid(&format!("'{}' must be between 0 and 1", name)))?,
+            )),
+            None => Ok(None),
+        }
+    };
+
+    let null_probability = probability("null_probability")?.unwrap_or(0.0);
+    let probability = probability("probability")?.unwrap_or(1.0 - null_probability);
+
+    if probability + null_probability > 1.0 + f64::EPSILON {
+        return Err(invalid(
+            "'probability' and 'null_probability' must not add up to more than 1",
+        ));
+    }
+
+    Ok((probability, null_probability, value))
+}
+
+/// Reads `{"value": .., "set": .., "attempts": ..}`. Values are unique per `set`, or per field
+/// path when no set is given.
+fn unique_definition<'a>(
+    definition: &'a Value,
+    path: &str,
+) -> anyhow::Result<(String, usize, &'a Value)> {
+    let invalid = |reason: &str| anyhow!("invalid {} at \"{}\": {}", UNIQUE, path, reason);
+
+    let value = definition
+        .get("value")
+        .ok_or_else(|| invalid("missing 'value'"))?;
+
+    let set = match definition.get("set") {
+        Some(set) => set
+            .as_str()
+            .ok_or_else(|| invalid("'set' must be a string"))?
+            .to_owned(),
+        None => path.to_owned(),
+    };
+
+    let attempts = match definition.get("attempts") {
+        Some(attempts) => attempts
+            .as_u64()
+            .filter(|attempts| *attempts > 0)
+            .ok_or_else(|| invalid("'attempts' must be a positive integer"))?
+            as usize,
+        None => UNIQUE_ATTEMPTS,
+    };
+
+    Ok((set, attempts, value))
+}
+
+fn no_unique_value(path: &str, attempts: usize) -> anyhow::Error {
+    anyhow!(
+        "could not render \"{}\": no new {} value after {} attempts, its values may have run out",
+        path,
+        UNIQUE,
+        attempts
+    )
+}
+
+/// Outcome of an `$optional` directive
+enum Presence {
+    Value,
+    Null,
+    Missing,
+}
+
+fn presence<R: Rng + ?Sized>(rng: &mut R, probability: f64, null_probability: f64) -> Presence {
+    let roll = rng.gen::<f64>();
+
+    if roll < null_probability {
+        Presence::Null
+    } else if roll < null_probability + probability {
+        Presence::Value
+    } else {
+        Presence::Missing
+    }
+}
+
+/// Rendered `$repeat` count, distributions returning floats are rounded
+fn repeat_count(count: &Value, path: &str) -> anyhow::Result<u64> {
+    count
+        .as_u64()
+        .or_else(|| {
+            count
+                .as_f64()
+                .filter(|count| *count >= 0.0)
+                .map(|count| count.round() as u64)
+        })
+        .or_else(|| count.as_str().and_then(|count| count.trim().parse().ok()))
+        .ok_or(anyhow!(
+            "could not render \"{}\": {} count should be a non-negative integer, got {}",
+            path,
+            REPEAT,
+            count
+        ))
+}
+
+fn is_template(leaf: &str) -> bool {
+    leaf.contains("{{") || leaf.contains("{%")
+}
+
+/// Wraps an expression so that the rendered output is its JSON encoded value
+fn typed_template(expression: &str) -> String {
+    format!(
+        "{{% set __value = {} %}}{{{{ __value | json_encode() }}}}",
+        expression
+    )
+}
+
+/// Returns the expression of a leaf consisting of exactly one `{{ ... }}` placeholder
+fn typed_expression(leaf: &str) -> Option<&str> {
+    let expression = leaf
+        .trim()
+        .strip_prefix("{{")?
+        .strip_suffix("}}")?
+        .trim_start_matches('-')
+        .trim_end_matches('-');
+
+    if expression.contains("{{") || expression.contains("}}") || expression.contains("{%") {
+        return None;
+    }
+
+    Some(expression.trim())
+}
+
+pub struct DocumentRendererFactory {}
+
+impl DocumentRendererFactory {
+    /// Creates a renderer with all the generators registered.
+    ///
+    /// Passing a `seed` makes every generator draw from one seeded rng, and passing `now` pins the
+    /// reference time, so that the same template always renders the same documents.
+    pub fn create_renderer(seed: Option<u64>, now: Option<DateTime<Utc>>) -> DocumentRenderer {
+        let mut document_renderer = DocumentRenderer::new(seed, now);
+
+        document_renderer.register_generators();
+
+        document_renderer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    const FORMAT_ISO: &str = "%FT%T%z";
+
+    use std::collections::{HashMap, HashSet};
+
+    use chrono::{DateTime, Utc};
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        dataset::Dataset,
+        dictionary::{Dictionary, Format},
+        document_context::DocumentContext,
+        document_renderer::DocumentRendererFactory,
+    };
+
+    fn document() -> DocumentContext<'static> {
+        DocumentContext {
+            seq: 0,
+            global_seq: 0,
+            index: "test",
+            count: 1,
+        }
+    }
+
+    #[test]
+    fn it_replaces_the_generators_with_values() {
+        let mut renderer = DocumentRendererFactory::create_renderer(None, None);
+
+        let result = renderer
+            .render(
+                r#"{
+            "values": {
+              "@timestamp": "{{date()}}",
+              "file.hash.md5": "{{hash()}}"
+            },
+            "index": {
+              "mappings": {
+                "properties": {
+                  "file.hash.md5": { "type": "keyword" },
+                  "@timestamp": { "type": "date" }
+                }
+              }
+            }
+          }
+        "#,
+            )
+            .unwrap();
+
+        assert_eq!(result.contains("date()"), false);
         assert_eq!(result.contains("hash()"), false);
 
         let dt = Utc::now();
 
         assert_eq!(result.contains(&dt.format(FORMAT_ISO).to_string()), true);
     }
+
+    #[test]
+    fn it_renders_the_same_documents_for_the_same_seed() {
+        let template = r#"{
+            "@timestamp": "{{date(sub_rnd_days=30)}}",
+            "user": "{{username()}}",
+            "hash": "{{hash()}}",
+            "level": "{{random_value(options='a|b|c')}}",
+            "n": "{{randomint(range=1000)}}",
+            "r": "{{get_random(end=1000)}}",
+            "ip": "{{ ip() }}"
+        }"#;
+
+        let now = "2022-11-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let mut first = DocumentRendererFactory::create_renderer(Some(42), Some(now));
+        let mut second = DocumentRendererFactory::create_renderer(Some(42), Some(now));
+
+        for _ in 0..10 {
+            assert_eq!(
+                first.render(template).unwrap(),
+                second.render(template).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn it_keeps_the_type_of_single_expression_leaves() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(1), None);
+
+        let result = renderer
+            .render_value(&json!({
+                "count": "{{randomint(range=100)}}",
+                "flag": "{{ 1 > 0 }}",
+                "tags": "{{ ['a', 'b'] | concat(with='c') }}",
+                "label": "id-{{randomint(range=100)}}",
+                "nested": [{ "n": "{{ 40 + 2 }}" }, 7, null]
+            }))
+            .unwrap();
+
+        assert!(result["count"].is_u64());
+        assert_eq!(result["flag"], json!(true));
+        assert_eq!(result["tags"], json!(["a", "b", "c"]));
+        assert!(result["label"].as_str().unwrap().starts_with("id-"));
+        assert_eq!(result["nested"], json!([{ "n": 42 }, 7, null]));
+    }
+
+    #[test]
+    fn it_renders_compiled_plans_like_uncompiled_templates() {
+        let template = json!({
+            "@timestamp": "{{date(sub_rnd_days=30)}}",
+            "count": "{{randomint(range=100)}}",
+            "url": { "full": "http://{{username()}}.com" },
+            "event": { "kind": "enrichment", "tags": ["a", 1], "sequence": "{{ doc.seq }}" }
+        });
+        let now = "2022-11-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let mut compiled = DocumentRendererFactory::create_renderer(Some(7), Some(now));
+        let mut uncompiled = DocumentRendererFactory::create_renderer(Some(7), Some(now));
+
+        let plan = compiled.compile(&template).unwrap();
+
+        for _ in 0..10 {
+            assert_eq!(
+                compiled.render_plan(&plan, &document()).unwrap(),
+                uncompiled.render_value(&template).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn it_escapes_generated_values() {
+        let mut renderer = DocumentRendererFactory::create_renderer(None, None);
+
+        let result = renderer
+            .render(
+                r#"{
+                "quote": "say {{ '\"hi\"' }}",
+                "path": "{{ 'C:\\Windows\\' }}",
+                "multiline": "{{ 'first\nsecond' }}",
+                "{{ 'dyn' ~ 'amic' }}": "key"
+            }"#,
+            )
+            .unwrap();
+
+        let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(result["quote"], json!("say \"hi\""));
+        assert_eq!(result["path"], json!("C:\\Windows\\"));
+        assert_eq!(result["multiline"], json!("first\nsecond"));
+        assert_eq!(result["dynamic"], json!("key"));
+    }
+
+    #[test]
+    fn it_reports_the_path_of_a_failing_leaf() {
+        let mut renderer = DocumentRendererFactory::create_renderer(None, None);
+
+        let plan = renderer
+            .compile(&json!({ "url": { "full": "{{ missing_variable }}" } }))
+            .unwrap();
+
+        let err = renderer.render_plan(&plan, &document()).unwrap_err();
+
+        assert!(err.to_string().contains("url.full"));
+    }
+
+    #[test]
+    fn it_exposes_the_document_context_to_templates() {
+        let mut renderer = DocumentRendererFactory::create_renderer(None, None);
+        renderer.set_run_id("run-1".to_owned());
+
+        let plan = renderer
+            .compile(&json!({
+                "id": "{{ fixture.index }}-{{ doc.seq }}",
+                "global": "{{ doc.global_seq }}",
+                "malicious": "{{ doc.seq % 10 == 0 }}",
+                "progress": "{{ doc.seq }}/{{ fixture.count }}",
+                "run": "{{ run.id }}"
+            }))
+            .unwrap();
+
+        let mut document = DocumentContext {
+            seq: 0,
+            global_seq: 100,
+            index: "logs",
+            count: 20,
+        };
+
+        let first = renderer.render_plan(&plan, &document).unwrap();
+        document.advance();
+        let second = renderer.render_plan(&plan, &document).unwrap();
+
+        assert_eq!(first["id"], json!("logs-0"));
+        assert_eq!(first["global"], json!(100));
+        assert_eq!(first["malicious"], json!(true));
+        assert_eq!(first["run"], json!("run-1"));
+        assert_eq!(second["id"], json!("logs-1"));
+        assert_eq!(second["malicious"], json!(false));
+        assert_eq!(second["progress"], json!("1/20"));
+    }
+
+    #[test]
+    fn it_draws_values_from_shared_pools() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(3), None);
+
+        renderer
+            .register_pools(&json!({
+                "hosts": { "size": 5, "value": "host-{{ doc.seq }}" }
+            }))
+            .unwrap();
+
+        let plan = renderer
+            .compile(&json!({
+                "indicator": "{{ pool(name='hosts', index=doc.seq) }}",
+                "event": "{{ pool(name='hosts', overlap=0.25, index=doc.seq) }}"
+            }))
+            .unwrap();
+
+        let mut document = document();
+        let mut matches = 0;
+
+        for seq in 0..100 {
+            let result = renderer.render_plan(&plan, &document).unwrap();
+
+            assert_eq!(result["indicator"], json!(format!("host-{}", seq % 5)));
+
+            let event = result["event"].as_str().unwrap();
+            let event_seq: usize = event.trim_start_matches("host-").parse().unwrap();
+
+            if event_seq < 5 {
+                matches += 1;
+            }
+
+            document.advance();
+        }
+
+        assert_eq!(matches, 25);
+    }
+
+    #[test]
+    fn it_exposes_the_fixture_time_model_as_doc_timestamp() {
+        let now = "2022-11-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(5), Some(now));
+
+        let template = renderer
+            .compile_template(&json!({
+                "time": { "start": "now-1h", "end": "now", "distribution": "increasing" },
+                "values": { "@timestamp": "{{ doc.timestamp }}" }
+            }))
+            .unwrap();
+
+        let mut document = DocumentContext {
+            seq: 0,
+            global_seq: 0,
+            index: "logs",
+            count: 60,
+        };
+
+        let mut previous = now - chrono::Duration::hours(1);
+
+        for _ in 0..60 {
+            let result = renderer.render_document(&template, &document).unwrap();
+            let timestamp = result["@timestamp"]
+                .as_str()
+                .unwrap()
+                .parse::<DateTime<Utc>>()
+                .unwrap();
+
+            assert!(timestamp >= previous && timestamp <= now);
+
+            previous = timestamp;
+            document.advance();
+        }
+    }
+
+    #[test]
+    fn it_exposes_now_as_doc_timestamp_without_a_time_model() {
+        let now = "2022-11-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(5), Some(now));
+
+        let template = renderer
+            .compile_template(&json!({
+                "values": {
+                    "@timestamp": "{{ doc.timestamp }}",
+                    "event.created": "{{ date(base=doc.timestamp, add_rnd_seconds=5) }}"
+                }
+            }))
+            .unwrap();
+
+        for _ in 0..10 {
+            let result = renderer.render_document(&template, &document()).unwrap();
+
+            assert_eq!(result["@timestamp"], "2022-11-20T12:00:00.000Z");
+
+            let created =
+                DateTime::parse_from_str(result["event.created"].as_str().unwrap(), "%FT%T%z")
+                    .unwrap();
+
+            assert!(created >= now && created <= now + chrono::Duration::seconds(5));
+        }
+    }
+
+    #[test]
+    fn it_generates_dates_within_ranges_and_formats() {
+        let now = "2022-11-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(9), Some(now));
+
+        let plan = renderer
+            .compile(&json!({
+                "range": "{{ date(from='now-15m', to='now', format='epoch_millis') }}",
+                "hours": "{{ date(sub_rnd_hours=2, format='rfc3339') }}",
+                "legacy": "{{ date(sub_rnd_days=0) }}",
+                "seconds": "{{ date(base='2022-11-01T00:00:00Z', add_rnd_seconds=5, format='epoch_second') }}",
+                "relative_base": "{{ date(base='now-1d', format='epoch_second') }}",
+                "custom": "{{ now(format='%Y/%m/%d') }}"
+            }))
+            .unwrap();
+
+        for _ in 0..20 {
+            let result = renderer.render_plan(&plan, &document()).unwrap();
+
+            let range = result["range"].as_i64().unwrap();
+            assert!(range >= now.timestamp_millis() - 15 * 60 * 1000);
+            assert!(range <= now.timestamp_millis());
+
+            let hours = result["hours"]
+                .as_str()
+                .unwrap()
+                .parse::<DateTime<Utc>>()
+                .unwrap();
+            assert!(hours >= now - chrono::Duration::hours(2) && hours <= now);
+
+            assert_eq!(result["legacy"], json!("2022-11-20T12:00:00+0000"));
+
+            let seconds = result["seconds"].as_i64().unwrap();
+            assert!((1667260800..=1667260805).contains(&seconds));
+
+            assert_eq!(result["relative_base"], json!(now.timestamp() - 24 * 3600));
+
+            assert_eq!(result["custom"], json!("2022/11/20"));
+        }
+    }
+
+    #[test]
+    fn it_picks_weighted_typed_choices() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(11), None);
+
+        let plan = renderer
+            .compile(&json!({
+                "severity": "{{ choice(options=['low', 'high'], weights=[80, 0]) }}",
+                "code": "{{ choice(options=[200, 404], weights=[0, 1]) }}",
+                "outcome": "{{ choice(options='success|failure', weights=[1, 0]) }}",
+                "endpoint": "{{ choice(options='10.0.0.1:8080|other') }}",
+                "any": "{{ choice(options=[true, false]) }}"
+            }))
+            .unwrap();
+
+        for _ in 0..20 {
+            let result = renderer.render_plan(&plan, &document()).unwrap();
+
+            assert_eq!(result["severity"], json!("low"));
+            assert_eq!(result["code"], json!(404));
+            assert_eq!(result["outcome"], json!("success"));
+            assert!(["10.0.0.1:8080", "other"].contains(&result["endpoint"].as_str().unwrap()));
+            assert!(result["any"].is_boolean());
+        }
+
+        let invalid = renderer
+            .compile(&json!({ "severity": "{{ choice(options=['low'], weights=[1, 2]) }}" }))
+            .unwrap();
+
+        let err = renderer.render_plan(&invalid, &document()).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("got 1 options but 2 weights"));
+    }
+
+    #[test]
+    fn it_generates_bounded_numbers() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(13), None);
+
+        let plan = renderer
+            .compile(&json!({
+                "dice": "{{ randomint(min=1, max=6) }}",
+                "legacy": "{{ randomint(range=3) }}",
+                "ratio": "{{ randomfloat(min=0.5, max=1.5, precision=2) }}",
+                "tenth": "{{ randomfloat(min=0, max=0.1, precision=1) }}",
+                "whole": "{{ randomfloat(min=0, max=10, precision=0) }}",
+                "unit": "{{ randomfloat(precision=0) }}",
+                "bytes": "{{ lognormal(mu=7, sigma=1.5, max=100000, precision=0) }}",
+                "latency": "{{ normal(mean=100, std_dev=15, min=0) }}",
+                "events": "{{ poisson(lambda=4) }}",
+                "rank": "{{ zipf(n=10, s=1.2) }}",
+                "interval": "{{ exponential(lambda=0.5) }}"
+            }))
+            .unwrap();
+
+        for _ in 0..50 {
+            let result = renderer.render_plan(&plan, &document()).unwrap();
+
+            assert!((1..=6).contains(&result["dice"].as_i64().unwrap()));
+            assert!((0..3).contains(&result["legacy"].as_i64().unwrap()));
+
+            let ratio = result["ratio"].as_f64().unwrap();
+            assert!((0.5..1.5).contains(&ratio));
+            assert_eq!((ratio * 100.0).round() / 100.0, ratio);
+
+            // rounding up to 'max' would include it
+            assert_eq!(result["tenth"], json!(0.0));
+            assert!((0..10).contains(&result["whole"].as_i64().unwrap()));
+            // the default 'max' of 1 is excluded too
+            assert_eq!(result["unit"], json!(0));
+
+            assert!((0..=100000).contains(&result["bytes"].as_i64().unwrap()));
+
+            assert!(result["latency"].as_f64().unwrap() >= 0.0);
+            assert!(result["events"].is_i64());
+            assert!((1..=10).contains(&result["rank"].as_i64().unwrap()));
+            assert!(result["interval"].as_f64().unwrap() >= 0.0);
+        }
+
+        let missing = renderer
+            .compile(&json!({ "n": "{{ randomint() }}" }))
+            .unwrap();
+
+        let err = renderer.render_plan(&missing, &document()).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("missing 'max'"));
+    }
+
+    #[test]
+    fn it_switches_faker_locales_per_call_and_per_template() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(17), None);
+
+        let template = renderer
+            .compile_template(&json!({
+                "locale": "zh_CN",
+                "values": {
+                    "default": "{{ firstname() }}",
+                    "japanese": "{{ name(locale='ja_JP') }}",
+                    "english": "{{ firstname(locale='en_US') }}"
+                }
+            }))
+            .unwrap();
+
+        let result = renderer.render_document(&template, &document()).unwrap();
+
+        assert!(!result["default"].as_str().unwrap().is_ascii());
+        assert!(!result["japanese"].as_str().unwrap().is_ascii());
+        assert!(result["english"].as_str().unwrap().is_ascii());
+
+        let unsupported = renderer
+            .compile(&json!({ "name": "{{ name(locale='xx_XX') }}" }))
+            .unwrap();
+
+        let err = renderer.render_plan(&unsupported, &document()).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("unsupported locale"));
+    }
+
+    #[test]
+    fn it_generates_ecs_field_formats() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(5), None);
+
+        let plan = renderer
+            .compile(&json!({
+                "md5": "{{ md5() }}",
+                "sha256": "{{ sha256() }}",
+                "port": "{{ port(kind='ephemeral') }}",
+                "status": "{{ http_status(class=4) }}",
+                "uuid": "{{ uuid() }}",
+                "url": "{{ url(scheme='http', domain='example.com') }}"
+            }))
+            .unwrap();
+
+        for _ in 0..100 {
+            let result = renderer.render_plan(&plan, &document()).unwrap();
+
+            let md5 = result["md5"].as_str().unwrap();
+            assert_eq!(md5.len(), 32);
+            assert!(md5.chars().all(|c| c.is_ascii_hexdigit()));
+            assert_eq!(result["sha256"].as_str().unwrap().len(), 64);
+            assert!(result["port"].as_u64().unwrap() >= 49152);
+            assert_eq!(result["status"].as_u64().unwrap() / 100, 4);
+
+            let uuid = result["uuid"].as_str().unwrap();
+            assert_eq!(uuid.len(), 36);
+            assert_eq!(&uuid[14..15], "4");
+
+            assert!(result["url"]
+                .as_str()
+                .unwrap()
+                .starts_with("http://example.com/"));
+        }
+    }
+
+    #[test]
+    fn it_keeps_host_addresses_stable() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(9), None);
+
+        let plan = renderer
+            .compile(&json!({
+                "source": "{{ ipv4(cidr=['10.0.0.0/8', '192.168.0.0/16'], host=doc.seq % 3) }}",
+                "destination": "{{ ipv6(cidr='2001:db8::/32') }}"
+            }))
+            .unwrap();
+
+        let mut hosts = HashMap::new();
+
+        for seq in 0..30 {
+            let document = DocumentContext { seq, ..document() };
+            let result = renderer.render_plan(&plan, &document).unwrap();
+
+            let source = result["source"].as_str().unwrap().to_owned();
+            assert!(source.starts_with("10.") || source.starts_with("192.168."));
+            assert_eq!(hosts.entry(seq % 3).or_insert(source.clone()), &source);
+
+            assert!(result["destination"]
+                .as_str()
+                .unwrap()
+                .starts_with("2001:db8:"));
+        }
+
+        let ranges = renderer
+            .compile(&json!({
+                "dmz": "{{ ipv4(cidr='172.16.0.0/12', host='web-1') }}",
+                "public": "{{ ipv4(public=true, host='web-1') }}"
+            }))
+            .unwrap();
+
+        let result = renderer.render_plan(&ranges, &document()).unwrap();
+
+        assert!(result["dmz"].as_str().unwrap().starts_with("172."));
+        assert!(!result["public"]
+            .as_str()
+            .unwrap()
+            .parse::<std::net::Ipv4Addr>()
+            .unwrap()
+            .is_private());
+
+        let mismatch = renderer
+            .compile(&json!({ "ip": "{{ ipv4(cidr='fd00::/8') }}" }))
+            .unwrap();
+
+        assert!(renderer.render_plan(&mismatch, &document()).is_err());
+    }
+
+    #[test]
+    fn it_generates_geo_points_and_shapes() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(21), None);
+
+        let plan = renderer
+            .compile(&json!({
+                "bbox": "{{ geo_point(bbox=[2, 48, 3, 49], format='array') }}",
+                "city": "{{ geo_point(city='tokyo', radius_km=10) }}",
+                "polygon": "{{ geo_shape(city='paris', points=6) }}",
+                "line": "{{ geo_shape(type='linestring', format='wkt') }}"
+            }))
+            .unwrap();
+
+        for _ in 0..100 {
+            let result = renderer.render_plan(&plan, &document()).unwrap();
+
+            let lon = result["bbox"][0].as_f64().unwrap();
+            let lat = result["bbox"][1].as_f64().unwrap();
+            assert!((2.0..=3.0).contains(&lon) && (48.0..=49.0).contains(&lat));
+
+            let lat = result["city"]["lat"].as_f64().unwrap();
+            assert!((lat - 35.6762).abs() < 0.1);
+
+            let ring = result["polygon"]["coordinates"][0].as_array().unwrap();
+            assert_eq!(result["polygon"]["type"], "Polygon");
+            assert_eq!(ring.len(), 7);
+            assert_eq!(ring[0], ring[6]);
+
+            assert!(result["line"].as_str().unwrap().starts_with("LINESTRING ("));
+        }
+
+        let unknown = renderer
+            .compile(&json!({ "location": "{{ geo_point(city='atlantis') }}" }))
+            .unwrap();
+
+        assert!(renderer.render_plan(&unknown, &document()).is_err());
+    }
+
+    #[test]
+    fn it_repeats_values_into_arrays() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(2), None);
+
+        let template = json!({
+            "related": {
+                "ip": { "$repeat": { "min": 1, "max": 3, "value": "{{ ipv4() }}" } }
+            },
+            "tags": { "$repeat": { "count": 2, "value": "tag-{{ doc.seq }}" } },
+            "processes": {
+                "$repeat": {
+                    "count": "{{ randomint(min=0, max=4) }}",
+                    "value": { "pid": "{{ pid() }}", "name": "bash" }
+                }
+            }
+        });
+
+        let plan = renderer.compile(&template).unwrap();
+
+        for _ in 0..100 {
+            let result = renderer.render_plan(&plan, &document()).unwrap();
+
+            let ips = result["related"]["ip"].as_array().unwrap();
+            assert!((1..=3).contains(&ips.len()));
+
+            assert_eq!(result["tags"], json!(["tag-0", "tag-0"]));
+
+            let processes = result["processes"].as_array().unwrap();
+            assert!(processes.len() <= 4);
+            assert!(processes
+                .iter()
+                .all(|process| process["pid"].is_u64() && process["name"] == "bash"));
+        }
+
+        let uncompiled = renderer
+            .render_value(&json!({ "$repeat": { "count": "{{ 1 + 1 }}", "value": "{{ md5() }}" } }))
+            .unwrap();
+
+        assert_eq!(uncompiled.as_array().unwrap().len(), 2);
+
+        assert!(renderer
+            .compile(&json!({ "ip": { "$repeat": { "min": 3, "max": 1, "value": "x" } } }))
+            .is_err());
+    }
+
+    #[test]
+    fn it_leaves_optional_values_out() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(4), None);
+
+        let plan = renderer
+            .compile(&json!({
+                "user": {
+                    "name": { "$optional": { "probability": 0.6, "null_probability": 0.1, "value": "{{ username() }}" } },
+                    "id": { "$optional": { "null_probability": 0.5, "value": "{{ uuid() }}" } }
+                },
+                "tags": ["always", { "$optional": { "probability": 0.5, "value": "sometimes" } }]
+            }))
+            .unwrap();
+
+        let (mut present, mut null, mut missing, mut ids) = (0, 0, 0, 0);
+
+        for _ in 0..1000 {
+            let result = renderer.render_plan(&plan, &document()).unwrap();
+
+            match result["user"].get("name") {
+                Some(Value::String(_)) => present += 1,
+                Some(Value::Null) => null += 1,
+                None => missing += 1,
+                Some(other) => panic!("unexpected name {}", other),
+            }
+
+            if result["user"].get("id").is_some() {
+                ids += 1;
+            }
+
+            assert!((1..=2).contains(&result["tags"].as_array().unwrap().len()));
+        }
+
+        assert!((550..650).contains(&present), "{} present", present);
+        assert!((70..130).contains(&null), "{} null", null);
+        assert!((250..350).contains(&missing), "{} missing", missing);
+        assert_eq!(ids, 1000);
+
+        assert!(renderer
+            .compile(&json!({ "a": { "$optional": { "probability": 0.8, "null_probability": 0.5, "value": 1 } } }))
+            .is_err());
+    }
+
+    #[test]
+    fn it_describes_generators_with_working_examples() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(8), None);
+
+        renderer
+            .register_pools(&json!({ "urls": { "size": 10, "value": "{{ url() }}" } }))
+            .unwrap();
+        renderer
+            .register_dataset(
+                "employees",
+                &json!({ "path": "employees.csv" }),
+                Dataset::parse("email\njane@example.com\n", Format::Csv).unwrap(),
+            )
+            .unwrap();
+        renderer
+            .register_entities(&json!({
+                "host": { "size": 5, "attributes": { "ip": "{{ ipv4() }}" } }
+            }))
+            .unwrap();
+
+        let generators = renderer.get_generators();
+
+        assert!(generators
+            .windows(2)
+            .all(|pair| pair[0].name < pair[1].name));
+
+        for generator in &generators {
+            assert!(!generator.description.is_empty(), "{}", generator.name);
+
+            let plan = renderer
+                .compile(&Value::String(generator.example.clone()))
+                .unwrap();
+
+            renderer
+                .render_plan(&plan, &document())
+                .unwrap_or_else(|err| panic!("{}: {:#}", generator.example, err));
+        }
+
+        let macaddress = generators
+            .iter()
+            .find(|generator| generator.name == "macaddress")
+            .unwrap();
+
+        assert!(macaddress.description.starts_with("Fake MAC address"));
+
+        let randomint = generators
+            .iter()
+            .find(|generator| generator.name == "randomint")
+            .unwrap()
+            .to_json();
+
+        assert_eq!(randomint["params"][0]["name"], "min");
+        assert_eq!(randomint["params"][0]["type"], "integer");
+        assert_eq!(randomint["params"][0]["default"], 0);
+    }
+
+    #[test]
+    fn it_registers_dictionaries_as_generators() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(6), None);
+
+        let definition = json!({ "path": "techniques.txt" });
+        let dictionary = || Dictionary::parse("T1059\nT1566\n", Format::Text, None, None).unwrap();
+
+        renderer
+            .register_dictionary("techniques", &definition, dictionary())
+            .unwrap();
+        renderer
+            .register_dictionary("techniques", &definition, dictionary())
+            .unwrap();
+
+        assert!(renderer
+            .register_dictionary("techniques", &json!({ "path": "other.txt" }), dictionary())
+            .is_err());
+        assert!(renderer
+            .register_dictionary("uuid", &definition, dictionary())
+            .is_err());
+
+        assert!(renderer
+            .get_generators()
+            .iter()
+            .any(|generator| generator.name == "techniques"));
+
+        let plan = renderer
+            .compile(&json!({ "threat.technique.id": "{{ techniques() }}" }))
+            .unwrap();
+        let result = renderer.render_plan(&plan, &document()).unwrap();
+
+        assert!(["T1059", "T1566"].contains(&result["threat.technique.id"].as_str().unwrap()));
+    }
+
+    #[test]
+    fn it_takes_the_fields_of_a_document_from_one_dataset_row() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(9), None);
+
+        let employees = (0..20)
+            .map(|id| format!("{},user{},user{}@example.com", id, id, id))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        renderer
+            .register_dataset(
+                "employees",
+                &json!({ "path": "employees.csv" }),
+                Dataset::parse(&format!("id,name,email\n{}", employees), Format::Csv).unwrap(),
+            )
+            .unwrap();
+
+        let template = renderer
+            .compile_template(&json!({
+                "values": {
+                    "user.id": "{{ datasets.employees.id }}",
+                    "user.email": "{{ datasets.employees.email }}",
+                    "source.user": "{{ dataset(name='employees', key=doc.seq % 2, field='name') }}",
+                    "destination.user": "{{ dataset(name='employees', key=doc.seq % 2) }}"
+                }
+            }))
+            .unwrap();
+
+        let mut document = document();
+        let mut ids = HashSet::new();
+        let mut keyed = HashMap::new();
+
+        for seq in 0..50 {
+            let result = renderer.render_document(&template, &document).unwrap();
+
+            let id = result["user.id"].as_str().unwrap();
+            assert_eq!(
+                result["user.email"],
+                json!(format!("user{}@example.com", id))
+            );
+            ids.insert(id.to_owned());
+
+            let user = keyed
+                .entry(seq % 2)
+                .or_insert(result["source.user"].clone());
+            assert_eq!(&result["source.user"], user);
+            assert_eq!(&result["destination.user"]["name"], user);
+
+            document.advance();
+        }
+
+        assert!(ids.len() > 2);
+    }
+
+    #[test]
+    fn it_keeps_the_attributes_of_an_entity_together() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(10), None);
+
+        renderer
+            .register_entities(&json!({
+                "host": {
+                    "size": 3,
+                    "attributes": {
+                        "name": "host-{{ doc.seq }}",
+                        "ip": "{{ ipv4(private=true) }}",
+                        "mac": "{{ macaddress() }}"
+                    }
+                }
+            }))
+            .unwrap();
+
+        assert!(renderer
+            .register_entities(&json!({ "host": { "size": 3, "attributes": {} } }))
+            .is_err());
+
+        let template = renderer
+            .compile_template(&json!({
+                "values": {
+                    "host.name": "{{ entities.host.name }}",
+                    "host.ip": "{{ entities.host.ip }}",
+                    "destination.ip": "{{ entity(type='host', key='gateway', field='ip') }}"
+                }
+            }))
+            .unwrap();
+
+        let mut document = document();
+        let mut hosts = HashMap::new();
+        let mut gateways = HashSet::new();
+
+        for _ in 0..50 {
+            let result = renderer.render_document(&template, &document).unwrap();
+
+            let name = result["host.name"].as_str().unwrap().to_owned();
+            let ip = hosts.entry(name).or_insert(result["host.ip"].clone());
+
+            assert_eq!(&result["host.ip"], ip);
+
+            gateways.insert(result["destination.ip"].to_string());
+
+            document.advance();
+        }
+
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(gateways.len(), 1);
+    }
+
+    #[test]
+    fn it_counts_sequences_and_keeps_values_unique() {
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(11), None);
+
+        let template = renderer
+            .compile_template(&json!({
+                "values": {
+                    "event.sequence": "{{ sequence() }}",
+                    "order_number": "ORD-{{ sequence(name='orders', start=1000, step=10, pad=6) }}",
+                    "transaction.id": { "$unique": { "value": "{{ randomint(max=19) }}" } }
+                }
+            }))
+            .unwrap();
+
+        let mut document = document();
+        let mut ids = HashSet::new();
+
+        for seq in 0..20 {
+            let result = renderer.render_document(&template, &document).unwrap();
+
+            assert_eq!(result["event.sequence"], json!(seq + 1));
+            assert_eq!(
+                result["order_number"],
+                json!(format!("ORD-{:06}", 1000 + seq * 10))
+            );
+            assert!(ids.insert(result["transaction.id"].as_i64().unwrap()));
+
+            document.advance();
+        }
+
+        let error = renderer.render_document(&template, &document).unwrap_err();
+
+        assert!(format!("{:#}", error).contains("transaction.id"));
+
+        let shared = json!({
+            "a": { "$unique": { "value": "{{ randomint(max=1) }}", "set": "bits" } },
+            "b": { "$unique": { "value": "{{ randomint(max=1) }}", "set": "bits" } }
+        });
+        let result = renderer.render_value(&shared).unwrap();
+
+        assert_ne!(result["a"], result["b"]);
+        assert!(renderer.render_value(&shared).is_err());
+    }
+
+    #[test]
+    fn it_renders_scenarios_as_sequences_sharing_state() {
+        let now = "2022-11-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut renderer = DocumentRendererFactory::create_renderer(Some(12), Some(now));
+
+        renderer
+            .register_entities(&json!({
+                "host": { "size": 10, "attributes": { "name": "host-{{ doc.seq }}" } }
+            }))
+            .unwrap();
+
+        let scenario = renderer
+            .compile_scenario(&json!({
+                "time": { "start": "now-1h", "end": "now", "distribution": "increasing" },
+                "scenario": {
+                    "state": { "pid": "{{ pid() }}", "session": "{{ uuid() }}" },
+                    "steps": [
+                        { "values": { "event.action": "logged-in", "@timestamp": "{{ doc.timestamp }}" } },
+                        {
+                            "gap": { "min": "1s", "max": "30s" },
+                            "values": {
+                                "event.action": "process-started",
+                                "process.pid": "{{ state.pid }}",
+                                "@timestamp": "{{ doc.timestamp }}"
+                            }
+                        },
+                        {
+                            "gap": "5m",
+                            "values": {
+                                "host.name": "{{ entities.host.name }}",
+                                "session": "{{ state.session }}",
+                                "step": "{{ scenario.seq }}-{{ scenario.step }}",
+                                "@timestamp": "{{ doc.timestamp }}"
+                            }
+                        }
+                    ]
+                }
+            }))
+            .unwrap();
+
+        let mut document = DocumentContext {
+            seq: 0,
+            global_seq: 0,
+            index: "logs",
+            count: 30,
+        };
+
+        let mut sessions = HashSet::new();
+
+        for sequence in 0..10 {
+            let documents = renderer.render_sequence(&scenario, &mut document).unwrap();
+
+            assert_eq!(documents.len(), 3);
+            assert_eq!(documents[0]["event.action"], json!("logged-in"));
+            assert!(documents[1]["process.pid"].is_u64());
+            assert_eq!(documents[2]["step"], json!(format!("{}-2", sequence)));
+            assert!(sessions.insert(documents[2]["session"].as_str().unwrap().to_owned()));
+
+            let timestamps: Vec<DateTime<Utc>> = documents
+                .iter()
+                .map(|document| document["@timestamp"].as_str().unwrap().parse().unwrap())
+                .collect();
+
+            let gap = timestamps[1] - timestamps[0];
+            assert!(gap >= chrono::Duration::seconds(1) && gap <= chrono::Duration::seconds(30));
+            assert_eq!(timestamps[2] - timestamps[1], chrono::Duration::minutes(5));
+        }
+
+        assert_eq!(document.seq, 30);
+    }
 }
@@ -0,0 +1,11 @@
+use crate::{locale::Locale, render_plan::RenderPlan, time_model::TimeModel};
+
+/// Fixture template compiled by the `DocumentRenderer`: the plan of its `values` along with the
+/// fixture-level settings
+#[derive(Debug)]
+pub struct DocumentTemplate {
+    pub values: RenderPlan,
+    pub time: Option<TimeModel>,
+    /// Default locale of the faker-backed generators for this fixture
+    pub locale: Option<Locale>,
+}
@@ -1,10 +1,21 @@
 use anyhow::{anyhow, Result};
 use elasticsearch::{
-    indices::{IndicesCreateParts, IndicesDeleteParts, IndicesExistsParts},
+    cluster::ClusterPutComponentTemplateParts,
+    http::response::Response,
+    indices::{
+        IndicesCreateDataStreamParts, IndicesCreateParts, IndicesDeleteDataStreamParts,
+        IndicesDeleteParts, IndicesExistsParts, IndicesPutIndexTemplateParts,
+    },
     Elasticsearch,
 };
+use serde_json::{json, Value};
 
-/// Creates (dropping previous one optionally) or returns existing index do append fake logs to
+/// Priority of the index templates which do not set one, above the built-in templates (eg.
+/// `logs-*-*` at 100) so that they win for the data streams they match
+const DEFAULT_TEMPLATE_PRIORITY: u64 = 500;
+
+/// Creates (dropping previous one optionally) or returns existing index do append fake logs to,
+/// or the same for a data stream along with its index and component templates
 pub struct EnsureIndex<'a> {
     client: &'a Elasticsearch,
 }
@@ -50,6 +61,106 @@ impl<'a> EnsureIndex<'a> {
         Ok(())
     }
 
+    async fn drop_data_stream(&self, data_stream: &str) -> Result<()> {
+        println!("Dropping data stream \"{}\"", data_stream);
+
+        let response = self
+            .client
+            .indices()
+            .delete_data_stream(IndicesDeleteDataStreamParts::Name(&[data_stream]))
+            .send()
+            .await?;
+
+        // missing data streams are fine, there is nothing to replace
+        if response.status_code() == 404 {
+            return Ok(());
+        }
+
+        check(response, &format!("drop data stream \"{}\"", data_stream)).await
+    }
+
+    async fn create_data_stream(&self, data_stream: &str) -> Result<()> {
+        println!("Creating data stream \"{}\"", data_stream);
+
+        let response = self
+            .client
+            .indices()
+            .create_data_stream(IndicesCreateDataStreamParts::Name(data_stream))
+            .send()
+            .await?;
+
+        check(response, &format!("create data stream \"{}\"", data_stream)).await
+    }
+
+    async fn put_component_template(&self, name: &str, payload: &Value) -> Result<()> {
+        println!("Putting component template \"{}\"", name);
+
+        let response = self
+            .client
+            .cluster()
+            .put_component_template(ClusterPutComponentTemplateParts::Name(name))
+            .body(payload)
+            .send()
+            .await?;
+
+        check(response, &format!("put component template \"{}\"", name)).await
+    }
+
+    async fn put_index_template(&self, name: &str, payload: &Value) -> Result<()> {
+        println!("Putting index template \"{}\"", name);
+
+        let response = self
+            .client
+            .indices()
+            .put_index_template(IndicesPutIndexTemplateParts::Name(name))
+            .body(payload)
+            .send()
+            .await?;
+
+        check(response, &format!("put index template \"{}\"", name)).await
+    }
+
+    /// Replaces the data stream with a new one, after putting its component templates and index
+    /// template (see [`data_stream_template`]).
+    pub async fn ensure_data_stream(
+        &self,
+        data_stream: &str,
+        index_template: &Value,
+        component_templates: Option<&Value>,
+        append: bool,
+    ) -> Result<()> {
+        if append {
+            if self.exists(data_stream).await? {
+                return Ok(());
+            }
+
+            return Err(anyhow!("data stream {} does not exist, cannot append. run this command without the 'append' flag first", data_stream));
+        }
+
+        let (name, index_template) = data_stream_template(data_stream, index_template)?;
+
+        self.drop_data_stream(data_stream).await?;
+
+        // a regular index would stand in the way of the data stream
+        if self.exists(data_stream).await? {
+            self.drop_index(data_stream).await?;
+        }
+
+        if let Some(component_templates) = component_templates {
+            let component_templates = component_templates.as_object().ok_or(anyhow!(
+                "component_templates should be an object of component templates, check your template"
+            ))?;
+
+            for (name, payload) in component_templates {
+                self.put_component_template(name, payload).await?;
+            }
+        }
+
+        self.put_index_template(&name, &index_template).await?;
+
+        self.create_data_stream(data_stream).await
+    }
+
     pub async fn ensure_index(
         &self,
         index: &str,
@@ -78,3 +189,86 @@ impl<'a> EnsureIndex<'a> {
         }
     }
 }
+
+/// Name and body of the index template of a data stream. The template is named after its `name`,
+/// or the data stream, matches the data stream unless it declares its own `index_patterns`, and
+/// gets a high `priority` unless it declares one
+fn data_stream_template(data_stream: &str, index_template: &Value) -> Result<(String, Value)> {
+    let mut index_template = index_template
+        .as_object()
+        .ok_or(anyhow!(
+            "index_template should be an object, check your template"
+        ))?
+        .clone();
+
+    let name = match index_template.remove("name") {
+        Some(Value::String(name)) => name,
+        Some(_) => return Err(anyhow!("index_template name should be a string")),
+        None => data_stream.to_owned(),
+    };
+
+    index_template
+        .entry("index_patterns")
+        .or_insert_with(|| json!([data_stream]));
+    index_template
+        .entry("data_stream")
+        .or_insert_with(|| json!({}));
+    index_template
+        .entry("priority")
+        .or_insert_with(|| json!(DEFAULT_TEMPLATE_PRIORITY));
+
+    Ok((name, Value::Object(index_template)))
+}
+
+/// Turns an unsuccessful response into an error with its body, which explains what went wrong
+pub(crate) async fn check(response: Response, action: &str) -> Result<()> {
+    let status = response.status_code();
+
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+
+    Err(anyhow!("could not {}: {} {}", action, status, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::data_stream_template;
+
+    #[test]
+    fn it_defaults_data_stream_templates() {
+        let (name, template) = data_stream_template(
+            "logs-fakebeat-default",
+            &json!({ "template": { "settings": { "number_of_replicas": 0 } } }),
+        )
+        .unwrap();
+
+        assert_eq!(name, "logs-fakebeat-default");
+        assert_eq!(
+            template,
+            json!({
+                "index_patterns": ["logs-fakebeat-default"],
+                "data_stream": {},
+                "priority": 500,
+                "template": { "settings": { "number_of_replicas": 0 } }
+            })
+        );
+
+        let (name, template) = data_stream_template(
+            "logs-fakebeat-default",
+            &json!({ "name": "fakebeat", "index_patterns": ["logs-fakebeat-*"], "priority": 200 }),
+        )
+        .unwrap();
+
+        assert_eq!(name, "fakebeat");
+        assert_eq!(template["index_patterns"], json!(["logs-fakebeat-*"]));
+        assert_eq!(template["priority"], json!(200));
+
+        assert!(data_stream_template("logs", &json!("logs")).is_err());
+        assert!(data_stream_template("logs", &json!({ "name": 1 })).is_err());
+    }
+}
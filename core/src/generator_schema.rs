@@ -0,0 +1,124 @@
+use std::fmt;
+
+use serde_json::{json, Value};
+
+/// Type of a generator parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamType {
+    Integer,
+    Number,
+    String,
+    Boolean,
+    List,
+    /// Any JSON value, eg. an identifier or the options of a choice
+    Any,
+}
+
+impl ParamType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParamType::Integer => "integer",
+            ParamType::Number => "number",
+            ParamType::String => "string",
+            ParamType::Boolean => "boolean",
+            ParamType::List => "list",
+            ParamType::Any => "any",
+        }
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parameter accepted by a generator
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamType,
+    pub required: bool,
+    /// Value used when the parameter is not passed, if there is a fixed one
+    pub default: Option<Value>,
+    pub description: String,
+}
+
+/// Description of a generator: what it produces, its parameters and how to call it
+#[derive(Debug, Clone)]
+pub struct GeneratorSchema {
+    pub name: String,
+    pub description: String,
+    pub params: Vec<Param>,
+    /// Example invocation, eg. `{{ randomint(min=1, max=6) }}`
+    pub example: String,
+}
+
+impl GeneratorSchema {
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            params: vec![],
+            example: format!("{{{{ {}() }}}}", name),
+        }
+    }
+
+    pub fn required(self, name: &str, kind: ParamType, description: &str) -> Self {
+        self.param(name, kind, true, None, description)
+    }
+
+    pub fn optional(self, name: &str, kind: ParamType, description: &str) -> Self {
+        self.param(name, kind, false, None, description)
+    }
+
+    /// Optional parameter with a fixed default value
+    pub fn with_default(
+        self,
+        name: &str,
+        kind: ParamType,
+        default: impl Into<Value>,
+        description: &str,
+    ) -> Self {
+        self.param(name, kind, false, Some(default.into()), description)
+    }
+
+    pub fn example(mut self, example: &str) -> Self {
+        self.example = example.to_owned();
+        self
+    }
+
+    fn param(
+        mut self,
+        name: &str,
+        kind: ParamType,
+        required: bool,
+        default: Option<Value>,
+        description: &str,
+    ) -> Self {
+        self.params.push(Param {
+            name: name.to_owned(),
+            kind,
+            required,
+            default,
+            description: description.to_owned(),
+        });
+        self
+    }
+
+    /// Machine-readable form of the schema, as printed by `fakebeat -g --generators-format json`
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "params": self.params.iter().map(|param| json!({
+                "name": param.name,
+                "type": param.kind.name(),
+                "required": param.required,
+                "default": param.default,
+                "description": param.description,
+            })).collect::<Vec<Value>>(),
+            "example": self.example,
+        })
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use tera::{Result, Value};
+
+use super::str_arg;
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+};
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new(
+            "choice",
+            "Weighted random choice, keeping the type of the picked value",
+        )
+        .required(
+            "options",
+            ParamType::Any,
+            "List of values, list of [value, weight] pairs or values separated with '|'",
+        )
+        .optional(
+            "weights",
+            ParamType::List,
+            "Weights of the options, eg. [80, 5]",
+        )
+        .example("{{ choice(options=['low', 'medium', 'high'], weights=[80, 15, 5]) }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let options = args
+                .get("options")
+                .ok_or("choice: missing 'options' parameter")?;
+
+            let (values, weights) = match options {
+                Value::String(options) => {
+                    let values: Vec<Value> = options.split('|').map(Value::from).collect();
+
+                    match args.get("weights") {
+                        Some(weights) => (values, parse_weights(weights)?),
+                        None => {
+                            let weights = vec![1.0; values.len()];
+                            (values, weights)
+                        }
+                    }
+                }
+                Value::Array(options) => match args.get("weights") {
+                    Some(weights) => (options.clone(), parse_weights(weights)?),
+                    None => parse_weighted_list(options),
+                },
+                _ => return Err("choice: 'options' must be a list or a string".into()),
+            };
+
+            choose_weighted(&mut *rng.lock().unwrap(), values, &weights)
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new(
+            "random_value",
+            "Random value from a set (see 'choice' for weighted values)",
+        )
+        .required("options", ParamType::String, "Values separated with '|'")
+        .example("{{ random_value(options='a|b|c') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let options = str_arg(args, "random_value", "options")?
+                .ok_or("random_value: missing 'options' parameter")?;
+
+            let values: Vec<Value> = options.split('|').map(Value::from).collect();
+            let weights = vec![1.0; values.len()];
+
+            choose_weighted(&mut *rng.lock().unwrap(), values, &weights)
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new(
+            "chance",
+            "Roll a dice within 'range', if 0 is rolled then return first of the 'options', else 2nd (see 'choice' for weighted values)",
+        )
+        .required("range", ParamType::Integer, "Number of faces of the dice")
+        .required("options", ParamType::String, "Two values separated with '|'")
+        .example("{{ chance(range=10, options='bad|good') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let range = args
+                .get("range")
+                .and_then(Value::as_u64)
+                .filter(|range| *range > 0)
+                .ok_or("chance: 'range' must be a positive integer")?;
+
+            let options = str_arg(args, "chance", "options")?
+                .ok_or("chance: missing 'options' parameter")?;
+
+            let values: Vec<Value> = options.split('|').map(Value::from).collect();
+
+            if values.len() != 2 {
+                return Err("chance: 'options' must contain exactly two values, eg. 'a|b'".into());
+            }
+
+            let weights = [1.0, (range - 1) as f64];
+
+            choose_weighted(&mut *rng.lock().unwrap(), values, &weights)
+        },
+    );
+}
+
+/// Splits a list of [value, weight] pairs, a plain list of values is weighted uniformly
+fn parse_weighted_list(options: &[Value]) -> (Vec<Value>, Vec<f64>) {
+    let pairs: Option<Vec<(Value, f64)>> = options
+        .iter()
+        .map(|option| match option.as_array().map(Vec::as_slice) {
+            Some([value, weight]) => weight.as_f64().map(|weight| (value.clone(), weight)),
+            _ => None,
+        })
+        .collect();
+
+    match pairs {
+        Some(pairs) if !pairs.is_empty() => pairs.into_iter().unzip(),
+        _ => (options.to_vec(), vec![1.0; options.len()]),
+    }
+}
+
+fn parse_weights(weights: &Value) -> Result<Vec<f64>> {
+    weights
+        .as_array()
+        .ok_or("choice: 'weights' must be a list of numbers")?
+        .iter()
+        .map(|weight| {
+            weight
+                .as_f64()
+                .ok_or_else(|| format!("choice: invalid weight {}", weight).into())
+        })
+        .collect()
+}
+
+fn choose_weighted<R: Rng + ?Sized>(
+    rng: &mut R,
+    mut values: Vec<Value>,
+    weights: &[f64],
+) -> Result<Value> {
+    if values.is_empty() {
+        return Err("choice: 'options' must not be empty".into());
+    }
+
+    if values.len() != weights.len() {
+        return Err(format!(
+            "choice: got {} options but {} weights",
+            values.len(),
+            weights.len()
+        )
+        .into());
+    }
+
+    let distribution = WeightedIndex::new(weights)
+        .map_err(|_| "choice: weights must be non-negative numbers, with at least one above 0")?;
+
+    Ok(values.swap_remove(distribution.sample(rng)))
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Duration, SecondsFormat, TimeZone, Utc,
+};
+use rand::Rng;
+use tera::{Result, Value};
+
+use super::{int_arg, str_arg};
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+    time_model::parse_time,
+};
+
+/// Default output format of the date generators
+pub(crate) const FORMAT_ISO: &str = "%FT%T%z";
+
+/// Units of the random offsets, as in the 'sub_rnd_days' or 'add_rnd_seconds' parameters
+const OFFSET_UNITS: [&str; 4] = ["days", "hours", "minutes", "seconds"];
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    let rng = renderer.rng();
+    let now = renderer.reference_now();
+
+    let mut schema =
+        GeneratorSchema::new("date", "Random date, now unless moved by the parameters")
+            .optional(
+                "from",
+                ParamType::String,
+                "Start of the range to pick the date from, absolute or relative like 'now-15m'",
+            )
+            .optional(
+                "to",
+                ParamType::String,
+                "End of the range to pick the date from, absolute or relative like 'now+1h'",
+            );
+
+    for unit in OFFSET_UNITS {
+        schema = schema
+            .optional(
+                &format!("sub_rnd_{}", unit),
+                ParamType::Integer,
+                &format!(
+                    "Moves the date back by a random offset of up to this many {}",
+                    unit
+                ),
+            )
+            .optional(
+                &format!("add_rnd_{}", unit),
+                ParamType::Integer,
+                &format!(
+                    "Moves the date forward by a random offset of up to this many {}",
+                    unit
+                ),
+            );
+    }
+
+    let schema = schema
+        .optional(
+            "base",
+            ParamType::Any,
+            "Reference date replacing now, eg. doc.timestamp, as a string or epoch millis",
+        )
+        .with_default(
+            "format",
+            ParamType::String,
+            "iso",
+            "iso, rfc3339, epoch_millis, epoch_second or a strftime pattern",
+        )
+        .example("{{ date(from='now-1d', format='epoch_millis') }}");
+
+    renderer.register_generator(
+        schema,
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let reference = match args.get("base") {
+                Some(base) => parse_date_value(base, now.unwrap_or_else(Utc::now))?,
+                None => now.unwrap_or_else(Utc::now),
+            };
+
+            let bound = |name: &str| -> Result<Option<DateTime<Utc>>> {
+                match str_arg(args, "date", name)? {
+                    Some(expression) => parse_time(expression, reference)
+                        .map(Some)
+                        .map_err(|err| format!("date: invalid '{}': {}", name, err).into()),
+                    None => Ok(None),
+                }
+            };
+
+            let from = bound("from")?;
+            let to = bound("to")?;
+
+            let mut rng = rng.lock().unwrap();
+
+            let mut date = match (from, to) {
+                (None, None) => reference,
+                (from, to) => {
+                    let from = from.unwrap_or(reference);
+                    let to = to.unwrap_or(reference);
+
+                    if from > to {
+                        return Err("date: 'from' should not be after 'to'".into());
+                    }
+
+                    from + random_offset(&mut *rng, to - from)
+                }
+            };
+
+            for unit in OFFSET_UNITS {
+                if let Some(amount) = int_arg(args, "date", &format!("sub_rnd_{}", unit))? {
+                    date -= random_offset(&mut *rng, offset_duration(unit, amount.abs()));
+                }
+
+                if let Some(amount) = int_arg(args, "date", &format!("add_rnd_{}", unit))? {
+                    date += random_offset(&mut *rng, offset_duration(unit, amount.abs()));
+                }
+            }
+
+            format_date(date, str_arg(args, "date", "format")?)
+        },
+    );
+
+    renderer.register_generator(
+        GeneratorSchema::new("now", "Current date, or the --now reference date")
+            .with_default(
+                "format",
+                ParamType::String,
+                "iso",
+                "Same formats as for 'date'",
+            )
+            .example("{{ now(format='rfc3339') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            format_date(
+                now.unwrap_or_else(Utc::now),
+                str_arg(args, "now", "format")?,
+            )
+        },
+    );
+}
+
+fn offset_duration(unit: &str, amount: i64) -> Duration {
+    match unit {
+        "days" => Duration::days(amount),
+        "hours" => Duration::hours(amount),
+        "minutes" => Duration::minutes(amount),
+        _ => Duration::seconds(amount),
+    }
+}
+
+fn random_offset<R: Rng + ?Sized>(rng: &mut R, span: Duration) -> Duration {
+    let span = span.num_milliseconds();
+
+    if span <= 0 {
+        return Duration::zero();
+    }
+
+    Duration::milliseconds(rng.gen_range(0..=span))
+}
+
+/// Reads a date passed to a generator, either as a string or as epoch milliseconds. Relative
+/// dates are resolved against `now`
+fn parse_date_value(value: &Value, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    match value {
+        Value::String(date) => parse_time(date, now)
+            .or_else(|_| {
+                DateTime::parse_from_str(date, FORMAT_ISO).map(|date| date.with_timezone(&Utc))
+            })
+            .map_err(|_| format!("date: could not parse the date \"{}\"", date).into()),
+        Value::Number(millis) => millis
+            .as_i64()
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+            .ok_or_else(|| format!("date: invalid epoch millis {}", millis).into()),
+        other => Err(format!("date: could not parse the date {}", other).into()),
+    }
+}
+
+fn format_date(date: DateTime<Utc>, format: Option<&str>) -> Result<Value> {
+    match format {
+        None | Some("iso") => Ok(date.format(FORMAT_ISO).to_string().into()),
+        Some("rfc3339") => Ok(date.to_rfc3339_opts(SecondsFormat::Millis, true).into()),
+        Some("epoch_millis") => Ok(date.timestamp_millis().into()),
+        Some("epoch_second") => Ok(date.timestamp().into()),
+        Some(pattern) => {
+            if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("date: invalid format \"{}\"", pattern).into());
+            }
+
+            Ok(date.format(pattern).to_string().into())
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use fake::{
+    faker::{
+        internet::en::{DomainSuffix, UserAgent},
+        lorem::en::Word,
+    },
+    Fake, Faker,
+};
+use rand::{distributions::Alphanumeric, seq::SliceRandom, Rng};
+use tera::{Result, Value};
+
+use super::{int_arg, str_arg};
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+};
+
+/// Hash generators with the hex length of their digest
+const HASHES: [(&str, usize); 4] = [("md5", 32), ("sha1", 40), ("sha256", 64), ("sha512", 128)];
+
+/// Highest pid on a default Linux install
+const DEFAULT_MAX_PID: i64 = 32768;
+
+const LINUX_EXECUTABLES: [&str; 12] = [
+    "/usr/bin/bash",
+    "/usr/bin/curl",
+    "/usr/bin/python3",
+    "/usr/bin/ssh",
+    "/usr/bin/sudo",
+    "/usr/bin/wget",
+    "/usr/sbin/cron",
+    "/usr/sbin/nginx",
+    "/usr/sbin/sshd",
+    "/usr/lib/systemd/systemd",
+    "/usr/local/bin/node",
+    "/opt/google/chrome/chrome",
+];
+
+const WINDOWS_EXECUTABLES: [&str; 10] = [
+    "C:\\Windows\\explorer.exe",
+    "C:\\Windows\\System32\\cmd.exe",
+    "C:\\Windows\\System32\\lsass.exe",
+    "C:\\Windows\\System32\\rundll32.exe",
+    "C:\\Windows\\System32\\svchost.exe",
+    "C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe",
+    "C:\\Windows\\System32\\wbem\\WmiPrvSE.exe",
+    "C:\\Program Files\\Google\\Chrome\\Application\\chrome.exe",
+    "C:\\Program Files\\Microsoft Office\\root\\Office16\\WINWORD.EXE",
+    "C:\\Program Files\\Mozilla Firefox\\firefox.exe",
+];
+
+const MACOS_EXECUTABLES: [&str; 8] = [
+    "/bin/zsh",
+    "/usr/bin/curl",
+    "/usr/bin/osascript",
+    "/usr/bin/python3",
+    "/usr/sbin/sshd",
+    "/sbin/launchd",
+    "/Applications/Safari.app/Contents/MacOS/Safari",
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+];
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new(
+            "hash",
+            "16-character long alpha-num hash (see md5, sha1, sha256 and sha512 for valid digests)",
+        ),
+        move |_: &HashMap<String, Value>| -> Result<Value> {
+            let mut rng = rng.lock().unwrap();
+
+            Ok((0..16)
+                .map(|_| char::from(rng.sample(Alphanumeric)))
+                .collect::<String>()
+                .into())
+        },
+    );
+
+    for (name, length) in HASHES {
+        let rng = renderer.rng();
+        renderer.register_generator(
+            GeneratorSchema::new(
+                name,
+                &format!("{}-character long hex digest, like a {} hash", length, name),
+            ),
+            move |_: &HashMap<String, Value>| -> Result<Value> {
+                Ok(hex(&mut *rng.lock().unwrap(), length).into())
+            },
+        );
+    }
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("port", "Port number")
+            .optional(
+                "kind",
+                ParamType::String,
+                "well_known (1-1023), registered (1024-49151) or ephemeral (49152-65535)",
+            )
+            .example("{{ port(kind='ephemeral') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let range = match str_arg(args, "port", "kind")? {
+                None => 1..=65535,
+                Some("well_known") => 1..=1023,
+                Some("registered") => 1024..=49151,
+                Some("ephemeral") => 49152..=65535,
+                Some(other) => {
+                    return Err(format!(
+                        "port: unknown kind \"{}\", expected well_known, registered or ephemeral",
+                        other
+                    )
+                    .into())
+                }
+            };
+
+            Ok(rng.lock().unwrap().gen_range::<u16, _>(range).into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("pid", "Process id").with_default(
+            "max",
+            ParamType::Integer,
+            DEFAULT_MAX_PID,
+            "Highest pid",
+        ),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let max = int_arg(args, "pid", "max")?.unwrap_or(DEFAULT_MAX_PID);
+
+            if max < 1 {
+                return Err("pid: 'max' must be positive".into());
+            }
+
+            Ok(rng.lock().unwrap().gen_range(1..=max).into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("executable", "Path of a common executable")
+            .with_default("os", ParamType::String, "linux", "linux, windows or macos")
+            .example("{{ executable(os='windows') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let executables: &[&str] = match str_arg(args, "executable", "os")? {
+                None | Some("linux") => &LINUX_EXECUTABLES,
+                Some("windows") => &WINDOWS_EXECUTABLES,
+                Some("macos") => &MACOS_EXECUTABLES,
+                Some(other) => {
+                    return Err(format!(
+                        "executable: unknown os \"{}\", expected linux, windows or macos",
+                        other
+                    )
+                    .into())
+                }
+            };
+
+            Ok((*executables.choose(&mut *rng.lock().unwrap()).unwrap()).into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("useragent", "Browser user agent string"),
+        move |_: &HashMap<String, Value>| -> Result<Value> {
+            Ok(UserAgent()
+                .fake_with_rng::<String, _>(&mut *rng.lock().unwrap())
+                .into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("http_method", "HTTP request method, eg. GET or POST"),
+        move |_: &HashMap<String, Value>| -> Result<Value> {
+            let method: http::Method = Faker.fake_with_rng(&mut *rng.lock().unwrap());
+
+            Ok(method.as_str().into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("http_status", "HTTP response status code")
+            .optional(
+                "class",
+                ParamType::Integer,
+                "Class of the code (1-5), eg. 2 for 2xx codes",
+            )
+            .example("{{ http_status(class=5) }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let class = int_arg(args, "http_status", "class")?;
+
+            if matches!(class, Some(class) if !(1..=5).contains(&class)) {
+                return Err("http_status: 'class' must be between 1 and 5".into());
+            }
+
+            let mut rng = rng.lock().unwrap();
+
+            loop {
+                let status: http::StatusCode = Faker.fake_with_rng(&mut *rng);
+
+                if class.is_none() || class == Some((status.as_u16() / 100) as i64) {
+                    return Ok(status.as_u16().into());
+                }
+            }
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("uuid", "Random (version 4) UUID"),
+        move |_: &HashMap<String, Value>| -> Result<Value> {
+            let mut bytes: [u8; 16] = rng.lock().unwrap().gen();
+
+            bytes[6] = (bytes[6] & 0x0f) | 0x40;
+            bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+            let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+            Ok(format!(
+                "{}-{}-{}-{}-{}",
+                &hex[0..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..32]
+            )
+            .into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("domain", "Domain name, eg. dolor.com"),
+        move |_: &HashMap<String, Value>| -> Result<Value> {
+            Ok(domain(&mut *rng.lock().unwrap()).into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("url", "Full URL, eg. https://dolor.com/sit/amet?id=42")
+            .with_default("scheme", ParamType::String, "https", "Scheme of the URL")
+            .optional(
+                "domain",
+                ParamType::String,
+                "Domain of the URL, random by default",
+            )
+            .example("{{ url(scheme='http', domain='example.com') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let scheme = str_arg(args, "url", "scheme")?.unwrap_or("https");
+            let mut rng = rng.lock().unwrap();
+
+            let domain = match str_arg(args, "url", "domain")? {
+                Some(domain) => domain.to_owned(),
+                None => domain(&mut *rng),
+            };
+
+            let path = (0..rng.gen_range(1..=3))
+                .map(|_| Word().fake_with_rng::<&str, _>(&mut *rng))
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let query = if rng.gen_bool(0.3) {
+                format!("?id={}", rng.gen_range(1..10000))
+            } else {
+                String::new()
+            };
+
+            Ok(format!("{}://{}/{}{}", scheme, domain, path, query).into())
+        },
+    );
+}
+
+/// Random lowercase hex string of the given length
+fn hex<R: Rng + ?Sized>(rng: &mut R, length: usize) -> String {
+    (0..length)
+        .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap())
+        .collect()
+}
+
+fn domain<R: Rng + ?Sized>(rng: &mut R) -> String {
+    format!(
+        "{}.{}",
+        Word().fake_with_rng::<&str, _>(rng),
+        DomainSuffix().fake_with_rng::<&str, _>(rng)
+    )
+}
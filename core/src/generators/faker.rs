@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use fake::Fake;
+use rand::rngs::StdRng;
+use tera::{Result, Value};
+
+use super::str_arg;
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+    locale::Locale,
+};
+
+/// Produces a value of a faker in the given locale
+type Faker = fn(Locale, &mut StdRng) -> String;
+
+/// Lists fakers as `name: module::Faker [locales]`, the locales being those the `fake` crate has
+/// its own data for, other locales falling back to english data
+macro_rules! fakers {
+    ($($name:ident: $module:ident::$faker:ident [$($locale:ident),+]),* $(,)?) => {
+        [$(
+            (
+                stringify!($name),
+                stringify!($faker),
+                &[$(Locale::$locale),+] as &[Locale],
+                (|locale: Locale, rng: &mut StdRng| -> String {
+                    match locale {
+                        Locale::En => fake::faker::$module::en::$faker().fake_with_rng(rng),
+                        Locale::FrFr => fake::faker::$module::fr_fr::$faker().fake_with_rng(rng),
+                        Locale::ZhCn => fake::faker::$module::zh_cn::$faker().fake_with_rng(rng),
+                        Locale::ZhTw => fake::faker::$module::zh_tw::$faker().fake_with_rng(rng),
+                        Locale::JaJp => fake::faker::$module::ja_jp::$faker().fake_with_rng(rng),
+                        Locale::PtBr => fake::faker::$module::pt_br::$faker().fake_with_rng(rng),
+                        Locale::ArSa => fake::faker::$module::ar_sa::$faker().fake_with_rng(rng),
+                    }
+                }) as Faker,
+            )
+        ),*]
+    };
+}
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    let fakers = fakers!(
+        // Numbers
+        digit: number::Digit [En],
+        // Internet
+        username: internet::Username [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        domainsuffix: internet::DomainSuffix [En, PtBr],
+        macaddress: internet::MACAddress [En],
+        freeemail: internet::FreeEmail [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        safeemail: internet::SafeEmail [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        freeemailprovider: internet::FreeEmailProvider [En, FrFr, PtBr],
+        // Lorem ipsum
+        word: lorem::Word [En],
+        // Name
+        firstname: name::FirstName [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        lastname: name::LastName [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        title: name::Title [En, ZhCn, ZhTw, JaJp],
+        suffix: name::Suffix [En],
+        name: name::Name [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        namewithtitle: name::NameWithTitle [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        // Filesystem
+        filepath: filesystem::FilePath [En],
+        filename: filesystem::FileName [En],
+        fileextension: filesystem::FileExtension [En],
+        dirpath: filesystem::DirPath [En],
+        // Company
+        companysuffix: company::CompanySuffix [En, JaJp, PtBr],
+        companyname: company::CompanyName [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        buzzword: company::Buzzword [En],
+        buzzwordmiddle: company::BuzzwordMiddle [En],
+        buzzwordtail: company::BuzzwordTail [En],
+        catchphase: company::CatchPhrase [En],
+        bsverb: company::BsVerb [En],
+        bsadj: company::BsAdj [En],
+        bsnoun: company::BsNoun [En],
+        bs: company::Bs [En],
+        profession: company::Profession [En],
+        industry: company::Industry [En],
+        // Address
+        cityprefix: address::CityPrefix [En],
+        citysuffix: address::CitySuffix [En],
+        cityname: address::CityName [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        countryname: address::CountryName [En],
+        countrycode: address::CountryCode [En],
+        streetsuffix: address::StreetSuffix [En, PtBr],
+        streetname: address::StreetName [En, FrFr, ZhCn, ZhTw, JaJp, PtBr, ArSa],
+        timezone: address::TimeZone [En],
+        statename: address::StateName [En, PtBr],
+        stateabbr: address::StateAbbr [En, PtBr],
+        secondaryaddresstype: address::SecondaryAddressType [En],
+        secondaryaddress: address::SecondaryAddress [En],
+        zipcode: address::ZipCode [En],
+        postcode: address::PostCode [En, PtBr],
+        buildingnumber: address::BuildingNumber [En],
+        latitude: address::Latitude [En],
+        longitude: address::Longitude [En]
+    );
+
+    for (name, faker_type, localized, faker) in fakers {
+        let localized = localized.iter().map(Locale::name).collect::<Vec<&str>>();
+
+        let schema = GeneratorSchema::new(
+            name,
+            &format!(
+                "Fake {}, localized for: {}",
+                describe(faker_type),
+                localized.join(", ")
+            ),
+        )
+        .optional(
+            "locale",
+            ParamType::String,
+            "Locale of the value, defaults to the template or --locale setting",
+        );
+
+        let rng = renderer.rng();
+        let locale = renderer.active_locale();
+        renderer.register_generator(
+            schema,
+            move |args: &HashMap<String, Value>| -> Result<Value> {
+                let locale = match str_arg(args, name, "locale")? {
+                    Some(locale) => locale
+                        .parse::<Locale>()
+                        .map_err(|err| format!("{}: {}", name, err))?,
+                    None => *locale.read().unwrap(),
+                };
+
+                Ok(faker(locale, &mut rng.lock().unwrap()).into())
+            },
+        );
+    }
+}
+
+/// Readable name of a faker type, eg. "free email provider" for `FreeEmailProvider`
+fn describe(faker_type: &str) -> String {
+    let chars: Vec<char> = faker_type.chars().collect();
+    let mut words = vec![String::new()];
+
+    for (i, c) in chars.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+
+        // a word starts after a lowercase letter, or at the last capital of an acronym
+        let starts_word = c.is_uppercase()
+            && match previous {
+                Some(previous) if previous.is_lowercase() => true,
+                Some(previous) if previous.is_uppercase() => {
+                    matches!(next, Some(next) if next.is_lowercase())
+                }
+                _ => false,
+            };
+
+        if starts_word {
+            words.push(String::new());
+        }
+
+        words.last_mut().unwrap().push(*c);
+    }
+
+    words
+        .into_iter()
+        .map(
+            |word| match word.chars().filter(|c| c.is_uppercase()).count() {
+                count if count > 1 => word,
+                _ => word.to_lowercase(),
+            },
+        )
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -0,0 +1,373 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use rand::{seq::SliceRandom, Rng};
+use serde_json::json;
+use tera::{Result, Value};
+
+use super::{float_arg, int_arg, str_arg};
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+};
+
+/// Kilometers per degree of latitude
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// Default spread of the points clustered around a city
+const DEFAULT_CITY_RADIUS_KM: f64 = 25.0;
+
+/// Default extent of the generated shapes
+const DEFAULT_SHAPE_SIZE_KM: f64 = 10.0;
+
+/// Cities the points can cluster around, with their (lon, lat)
+const CITIES: [(&str, f64, f64); 20] = [
+    ("amsterdam", 4.9041, 52.3676),
+    ("berlin", 13.4050, 52.5200),
+    ("buenos_aires", -58.3816, -34.6037),
+    ("cairo", 31.2357, 30.0444),
+    ("chicago", -87.6298, 41.8781),
+    ("dubai", 55.2708, 25.2048),
+    ("hong_kong", 114.1694, 22.3193),
+    ("johannesburg", 28.0473, -26.2041),
+    ("london", -0.1276, 51.5072),
+    ("los_angeles", -118.2437, 34.0522),
+    ("madrid", -3.7038, 40.4168),
+    ("mumbai", 72.8777, 19.0760),
+    ("new_york", -74.0060, 40.7128),
+    ("paris", 2.3522, 48.8566),
+    ("san_francisco", -122.4194, 37.7749),
+    ("sao_paulo", -46.6333, -23.5505),
+    ("singapore", 103.8198, 1.3521),
+    ("sydney", 151.2093, -33.8688),
+    ("tokyo", 139.6503, 35.6762),
+    ("toronto", -79.3832, 43.6532),
+];
+
+/// (lon, lat) pair, in the order used by GeoJSON and WKT
+type Point = (f64, f64);
+
+/// Where the generated points lie
+enum Area {
+    World,
+    Bbox { min: Point, max: Point },
+    Circle { centers: Vec<Point>, radius_km: f64 },
+}
+
+impl Area {
+    fn from_args(generator: &str, args: &HashMap<String, Value>) -> Result<Self> {
+        let radius_km = float_arg(args, generator, "radius_km")?;
+
+        if matches!(radius_km, Some(radius) if radius < 0.0) {
+            return Err(format!("{}: 'radius_km' must not be negative", generator).into());
+        }
+
+        match (args.get("bbox"), args.get("center"), args.get("city")) {
+            (None, None, None) => Ok(Area::World),
+            (Some(bbox), None, None) => {
+                let bbox = numbers(generator, "bbox", bbox, 4)?;
+
+                if bbox[0] > bbox[2] || bbox[1] > bbox[3] {
+                    return Err(format!(
+                        "{}: 'bbox' must be [min_lon, min_lat, max_lon, max_lat]",
+                        generator
+                    )
+                    .into());
+                }
+
+                Ok(Area::Bbox {
+                    min: (bbox[0], bbox[1]),
+                    max: (bbox[2], bbox[3]),
+                })
+            }
+            (None, Some(center), None) => {
+                let center = numbers(generator, "center", center, 2)?;
+
+                Ok(Area::Circle {
+                    centers: vec![(center[0], center[1])],
+                    radius_km: radius_km
+                        .ok_or(format!("{}: 'center' requires 'radius_km'", generator))?,
+                })
+            }
+            (None, None, Some(city)) => Ok(Area::Circle {
+                centers: cities(generator, city)?,
+                radius_km: radius_km.unwrap_or(DEFAULT_CITY_RADIUS_KM),
+            }),
+            _ => Err(format!("{}: use only one of 'bbox', 'center' and 'city'", generator).into()),
+        }
+    }
+
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Point {
+        match self {
+            Area::World => (rng.gen_range(-180.0..=180.0), rng.gen_range(-90.0..=90.0)),
+            Area::Bbox { min, max } => (rng.gen_range(min.0..=max.0), rng.gen_range(min.1..=max.1)),
+            Area::Circle { centers, radius_km } => {
+                // square root keeps the points evenly spread over the disk
+                let distance = radius_km * rng.gen::<f64>().sqrt();
+
+                offset(
+                    *centers.choose(rng).unwrap(),
+                    distance,
+                    rng.gen_range(0.0..2.0 * PI),
+                )
+            }
+        }
+    }
+}
+
+/// Point `distance_km` away from `point` in the direction of `angle` (radians, counterclockwise
+/// from east)
+fn offset(point: Point, distance_km: f64, angle: f64) -> Point {
+    let lat = (point.1 + distance_km * angle.sin() / KM_PER_DEGREE).clamp(-90.0, 90.0);
+    let lon_km = KM_PER_DEGREE * lat.to_radians().cos().max(0.01);
+    let lon = point.0 + distance_km * angle.cos() / lon_km;
+
+    // wrap around the antimeridian
+    let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+
+    (round(lon), round(lat))
+}
+
+/// Roughly 10cm precision, plenty for test data
+fn round(coordinate: f64) -> f64 {
+    (coordinate * 1e6).round() / 1e6
+}
+
+fn numbers(generator: &str, name: &str, value: &Value, count: usize) -> Result<Vec<f64>> {
+    value
+        .as_array()
+        .filter(|values| values.len() == count)
+        .and_then(|values| values.iter().map(Value::as_f64).collect::<Option<Vec<_>>>())
+        .ok_or(
+            format!(
+                "{}: '{}' must be a list of {} numbers",
+                generator, name, count
+            )
+            .into(),
+        )
+}
+
+fn cities(generator: &str, city: &Value) -> Result<Vec<Point>> {
+    let names = match city {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+
+    if names.is_empty() {
+        return Err(format!(
+            "{}: 'city' must be a city name or a list of them",
+            generator
+        )
+        .into());
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            CITIES
+                .iter()
+                .find(|(city, _, _)| city.eq_ignore_ascii_case(name))
+                .map(|(_, lon, lat)| (*lon, *lat))
+                .ok_or(
+                    format!(
+                        "{}: unknown city \"{}\", expected one of {}",
+                        generator,
+                        name,
+                        CITIES.map(|(city, _, _)| city).join(", ")
+                    )
+                    .into(),
+                )
+        })
+        .collect()
+}
+
+fn format_point(generator: &str, point: Point, format: Option<&str>) -> Result<Value> {
+    let (lon, lat) = point;
+
+    match format {
+        None | Some("object") => Ok(json!({ "lat": lat, "lon": lon })),
+        Some("array") => Ok(json!([lon, lat])),
+        Some("wkt") => Ok(format!("POINT ({} {})", lon, lat).into()),
+        Some(other) => Err(format!(
+            "{}: unknown format \"{}\", expected object, array or wkt",
+            generator, other
+        )
+        .into()),
+    }
+}
+
+fn format_shape(
+    generator: &str,
+    kind: &str,
+    points: &[Point],
+    format: Option<&str>,
+) -> Result<Value> {
+    let coordinates = points
+        .iter()
+        .map(|(lon, lat)| json!([lon, lat]))
+        .collect::<Vec<_>>();
+    let wkt = points
+        .iter()
+        .map(|(lon, lat)| format!("{} {}", lon, lat))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    match (format, kind) {
+        (None | Some("geojson"), "polygon") => {
+            Ok(json!({ "type": "Polygon", "coordinates": [coordinates] }))
+        }
+        (None | Some("geojson"), _) => {
+            Ok(json!({ "type": "LineString", "coordinates": coordinates }))
+        }
+        (Some("wkt"), "polygon") => Ok(format!("POLYGON (({}))", wkt).into()),
+        (Some("wkt"), _) => Ok(format!("LINESTRING ({})", wkt).into()),
+        (Some(other), _) => Err(format!(
+            "{}: unknown format \"{}\", expected geojson or wkt",
+            generator, other
+        )
+        .into()),
+    }
+}
+
+/// Parameters locating both geo generators
+fn located(schema: GeneratorSchema) -> GeneratorSchema {
+    schema
+        .optional(
+            "bbox",
+            ParamType::List,
+            "Bounding box, [min_lon, min_lat, max_lon, max_lat]",
+        )
+        .optional(
+            "center",
+            ParamType::List,
+            "Center of the area, [lon, lat], requires 'radius_km'",
+        )
+        .optional(
+            "city",
+            ParamType::Any,
+            "City name or list of them to cluster around, eg. ['paris', 'tokyo']",
+        )
+        .optional(
+            "radius_km",
+            ParamType::Number,
+            "Radius around 'center' or 'city', 25 by default for cities",
+        )
+}
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    let rng = renderer.rng();
+    renderer.register_generator(
+        located(GeneratorSchema::new(
+            "geo_point",
+            "Point for geo_point fields, anywhere unless restricted",
+        ))
+        .with_default(
+            "format",
+            ParamType::String,
+            "object",
+            "object, array or wkt",
+        )
+        .example("{{ geo_point(city=['paris', 'tokyo'], format='array') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let area = Area::from_args("geo_point", args)?;
+            let point = area.sample(&mut *rng.lock().unwrap());
+
+            format_point(
+                "geo_point",
+                (round(point.0), round(point.1)),
+                str_arg(args, "geo_point", "format")?,
+            )
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        located(GeneratorSchema::new(
+            "geo_shape",
+            "Shape for geo_shape fields, anywhere unless restricted",
+        ))
+        .with_default(
+            "type",
+            ParamType::String,
+            "polygon",
+            "polygon or linestring",
+        )
+        .with_default("points", ParamType::Integer, 5, "Number of vertices")
+        .with_default(
+            "size_km",
+            ParamType::Number,
+            DEFAULT_SHAPE_SIZE_KM,
+            "Approximate extent of the shape",
+        )
+        .with_default("format", ParamType::String, "geojson", "geojson or wkt")
+        .example("{{ geo_shape(type='linestring', city='london', format='wkt') }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let area = Area::from_args("geo_shape", args)?;
+            let kind = str_arg(args, "geo_shape", "type")?.unwrap_or("polygon");
+            let size_km = float_arg(args, "geo_shape", "size_km")?.unwrap_or(DEFAULT_SHAPE_SIZE_KM);
+            let vertices = int_arg(args, "geo_shape", "points")?.unwrap_or(5);
+
+            let min_vertices = if kind == "polygon" { 3 } else { 2 };
+
+            if vertices < min_vertices {
+                return Err(format!(
+                    "geo_shape: a {} needs at least {} points",
+                    kind, min_vertices
+                )
+                .into());
+            }
+
+            if size_km <= 0.0 {
+                return Err("geo_shape: 'size_km' must be positive".into());
+            }
+
+            let mut rng = rng.lock().unwrap();
+            let center = area.sample(&mut *rng);
+
+            let points = match kind {
+                "polygon" => {
+                    // vertices sorted by angle around the center never cross, and go counterclockwise
+                    let mut angles: Vec<f64> = (0..vertices)
+                        .map(|_| rng.gen_range(0.0..2.0 * PI))
+                        .collect();
+                    angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+                    let mut points: Vec<Point> = angles
+                        .into_iter()
+                        .map(|angle| offset(center, size_km * rng.gen_range(0.25..=0.5), angle))
+                        .collect();
+                    points.push(points[0]);
+
+                    points
+                }
+                "linestring" => {
+                    let step = size_km / (vertices - 1) as f64;
+                    let mut heading = rng.gen_range(0.0..2.0 * PI);
+
+                    let mut points = vec![(round(center.0), round(center.1))];
+
+                    for _ in 1..vertices {
+                        heading += rng.gen_range(-PI / 4.0..=PI / 4.0);
+                        points.push(offset(*points.last().unwrap(), step, heading));
+                    }
+
+                    points
+                }
+                other => {
+                    return Err(format!(
+                        "geo_shape: unknown type \"{}\", expected polygon or linestring",
+                        other
+                    )
+                    .into())
+                }
+            };
+
+            format_shape(
+                "geo_shape",
+                kind,
+                &points,
+                str_arg(args, "geo_shape", "format")?,
+            )
+        },
+    );
+}
@@ -0,0 +1,60 @@
+//! Families of generators registered by the `DocumentRenderer`
+
+use std::collections::HashMap;
+
+use tera::{Result, Value};
+
+pub(crate) mod choice;
+pub(crate) mod date;
+pub(crate) mod ecs;
+pub(crate) mod faker;
+pub(crate) mod geo;
+pub(crate) mod network;
+pub(crate) mod numbers;
+pub(crate) mod records;
+pub(crate) mod sequence;
+
+/// Reads an optional integer argument of a generator
+pub(crate) fn int_arg(
+    args: &HashMap<String, Value>,
+    generator: &str,
+    name: &str,
+) -> Result<Option<i64>> {
+    match args.get(name) {
+        Some(value) => match value.as_i64() {
+            Some(value) => Ok(Some(value)),
+            None => Err(format!("{}: '{}' must be an integer", generator, name).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Reads an optional numeric argument of a generator
+pub(crate) fn float_arg(
+    args: &HashMap<String, Value>,
+    generator: &str,
+    name: &str,
+) -> Result<Option<f64>> {
+    match args.get(name) {
+        Some(value) => match value.as_f64() {
+            Some(value) => Ok(Some(value)),
+            None => Err(format!("{}: '{}' must be a number", generator, name).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Reads an optional string argument of a generator
+pub(crate) fn str_arg<'a>(
+    args: &'a HashMap<String, Value>,
+    generator: &str,
+    name: &str,
+) -> Result<Option<&'a str>> {
+    match args.get(name) {
+        Some(value) => match value.as_str() {
+            Some(value) => Ok(Some(value)),
+            None => Err(format!("{}: '{}' must be a string", generator, name).into()),
+        },
+        None => Ok(None),
+    }
+}
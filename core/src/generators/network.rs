@@ -0,0 +1,387 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::{Arc, Mutex},
+};
+
+use rand::{seq::SliceRandom, Rng};
+use tera::{Result, Value};
+
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+};
+
+/// RFC 1918 ranges
+const PRIVATE_V4: [&str; 3] = ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"];
+
+/// Ranges which are neither private nor publicly routable
+const RESERVED_V4: [&str; 12] = [
+    "0.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "255.255.255.255/32",
+];
+
+/// Unique local addresses
+const PRIVATE_V6: [&str; 1] = ["fc00::/7"];
+
+/// Global unicast addresses
+const PUBLIC_V6: [&str; 1] = ["2000::/3"];
+
+/// Documentation prefix, carved out of the global unicast range
+const RESERVED_V6: [&str; 1] = ["2001:db8::/32"];
+
+/// IP network, eg. 10.0.0.0/8 or 2001:db8::/32
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(cidr: &str) -> std::result::Result<Self, String> {
+        let invalid = || format!("invalid CIDR \"{}\"", cidr);
+
+        let (address, prefix) = cidr.split_once('/').unwrap_or((cidr, ""));
+        let network: IpAddr = address.trim().parse().map_err(|_| invalid())?;
+
+        let width = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        let prefix = match prefix.trim() {
+            "" => width,
+            prefix => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= width)
+                .ok_or_else(invalid)?,
+        };
+
+        Ok(Self { network, prefix })
+    }
+
+    /// Mask of the host bits, within the width of the address family
+    fn host_mask(&self) -> u128 {
+        let width = self.width();
+
+        match self.prefix {
+            0 => u128::MAX >> (128 - width),
+            prefix => (1u128 << (width - prefix as u32)) - 1,
+        }
+    }
+
+    fn width(&self) -> u32 {
+        match self.network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    fn bits(address: IpAddr) -> u128 {
+        match address {
+            IpAddr::V4(address) => u32::from(address) as u128,
+            IpAddr::V6(address) => u128::from(address),
+        }
+    }
+
+    fn contains(&self, address: IpAddr) -> bool {
+        self.network.is_ipv4() == address.is_ipv4()
+            && Self::bits(address) & !self.host_mask()
+                == Self::bits(self.network) & !self.host_mask()
+    }
+
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> IpAddr {
+        let mask = self.host_mask();
+        let bits = (Self::bits(self.network) & !mask) | (rng.gen::<u128>() & mask);
+
+        match self.network {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+        }
+    }
+}
+
+fn parse_all(cidrs: &[&str]) -> Vec<Cidr> {
+    cidrs
+        .iter()
+        .map(|cidr| Cidr::parse(cidr).unwrap())
+        .collect()
+}
+
+/// Address space of a generator: the ranges to draw from and the ranges to avoid
+struct Space {
+    ranges: Vec<Cidr>,
+    excluded: Vec<Cidr>,
+}
+
+impl Space {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<IpAddr> {
+        // excluded ranges are small compared to the sampled ones, retries are rare
+        for _ in 0..1000 {
+            let address = self.ranges.choose(rng)?.sample(rng);
+
+            if !self.excluded.iter().any(|cidr| cidr.contains(address)) {
+                return Some(address);
+            }
+        }
+
+        None
+    }
+}
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    register_ip(renderer, "ipv4", false);
+    register_ip(renderer, "ipv6", true);
+    register_any_ip(renderer);
+}
+
+/// Registers `ip`, drawing an IPv4 or an IPv6 address evenly
+fn register_any_ip(renderer: &mut DocumentRenderer) {
+    let space = Space {
+        ranges: parse_all(&["0.0.0.0/0", "::/0"]),
+        excluded: vec![],
+    };
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("ip", "Random IPv4 or IPv6 address").example("{{ ip() }}"),
+        move |_: &HashMap<String, Value>| -> Result<Value> {
+            Ok(space
+                .sample(&mut *rng.lock().unwrap())
+                .ok_or("ip: no address available")?
+                .to_string()
+                .into())
+        },
+    );
+}
+
+fn register_ip(renderer: &mut DocumentRenderer, name: &'static str, v6: bool) {
+    let (private, public, reserved) = if v6 {
+        (
+            parse_all(&PRIVATE_V6),
+            parse_all(&PUBLIC_V6),
+            parse_all(&RESERVED_V6),
+        )
+    } else {
+        (
+            parse_all(&PRIVATE_V4),
+            parse_all(&["0.0.0.0/0"]),
+            [parse_all(&PRIVATE_V4), parse_all(&RESERVED_V4)].concat(),
+        )
+    };
+
+    let any = if v6 { "::/0" } else { "0.0.0.0/0" };
+    let example = if v6 { "fd00::/8" } else { "10.0.0.0/8" };
+
+    // addresses already given to hosts in each range, so that a host keeps its address for the
+    // whole run
+    let hosts: Arc<Mutex<HashMap<(String, String), Value>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new(name, &format!("Random {} address", name))
+            .optional(
+                "cidr",
+                ParamType::Any,
+                &format!(
+                    "Range like '{}', or a list of ranges picked evenly",
+                    example
+                ),
+            )
+            .with_default(
+                "private",
+                ParamType::Boolean,
+                false,
+                "Private addresses only",
+            )
+            .with_default(
+                "public",
+                ParamType::Boolean,
+                false,
+                "Publicly routable addresses only",
+            )
+            .optional(
+                "host",
+                ParamType::Any,
+                "Host identifier, always getting the same address",
+            )
+            .example(&format!(
+                "{{{{ {}(cidr='{}', host=doc.seq % 10) }}}}",
+                name, example
+            )),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let flag = |flag: &str| -> Result<bool> {
+                match args.get(flag) {
+                    None => Ok(false),
+                    Some(Value::Bool(value)) => Ok(*value),
+                    Some(_) => Err(format!("{}: '{}' must be a boolean", name, flag).into()),
+                }
+            };
+
+            // the range spec is part of the host key, a host asked for in another range gets
+            // another address
+            let (spec, space) = match (args.get("cidr"), flag("private")?, flag("public")?) {
+                (Some(cidr), false, false) => (
+                    cidr.to_string(),
+                    Space {
+                        ranges: parse_cidrs(name, cidr, v6)?,
+                        excluded: vec![],
+                    },
+                ),
+                (None, true, false) => (
+                    "private".to_owned(),
+                    Space {
+                        ranges: private.clone(),
+                        excluded: vec![],
+                    },
+                ),
+                (None, false, true) => (
+                    "public".to_owned(),
+                    Space {
+                        ranges: public.clone(),
+                        excluded: reserved.clone(),
+                    },
+                ),
+                (None, false, false) => (
+                    any.to_owned(),
+                    Space {
+                        ranges: parse_all(&[any]),
+                        excluded: vec![],
+                    },
+                ),
+                _ => {
+                    return Err(
+                        format!("{}: use only one of 'cidr', 'private' and 'public'", name).into(),
+                    )
+                }
+            };
+
+            // any value identifies a host, eg. a name or doc.seq % 10
+            let host = args.get("host").map(|host| match host {
+                Value::String(host) => (host.clone(), spec),
+                host => (host.to_string(), spec),
+            });
+
+            if let Some(address) = host
+                .as_ref()
+                .and_then(|host| hosts.lock().unwrap().get(host).cloned())
+            {
+                return Ok(address);
+            }
+
+            let address: Value = space
+                .sample(&mut *rng.lock().unwrap())
+                .ok_or(format!("{}: no address available in the given range", name))?
+                .to_string()
+                .into();
+
+            if let Some(host) = host {
+                hosts.lock().unwrap().insert(host, address.clone());
+            }
+
+            Ok(address)
+        },
+    );
+}
+
+fn parse_cidrs(generator: &str, cidr: &Value, v6: bool) -> Result<Vec<Cidr>> {
+    let cidrs = match cidr {
+        Value::String(cidr) => vec![cidr.as_str()],
+        Value::Array(cidrs) => cidrs
+            .iter()
+            .map(|cidr| {
+                cidr.as_str().ok_or(format!(
+                    "{}: 'cidr' must be a string or a list of strings",
+                    generator
+                ))
+            })
+            .collect::<std::result::Result<_, _>>()?,
+        _ => {
+            return Err(format!(
+                "{}: 'cidr' must be a string or a list of strings",
+                generator
+            )
+            .into())
+        }
+    };
+
+    if cidrs.is_empty() {
+        return Err(format!("{}: 'cidr' must not be empty", generator).into());
+    }
+
+    cidrs
+        .into_iter()
+        .map(|cidr| {
+            let parsed = Cidr::parse(cidr).map_err(|err| format!("{}: {}", generator, err))?;
+
+            if parsed.network.is_ipv6() != v6 {
+                return Err(
+                    format!("{}: \"{}\" is not a {} range", generator, cidr, generator).into(),
+                );
+            }
+
+            Ok(parsed)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{parse_all, Cidr, Space, PRIVATE_V4, RESERVED_V4};
+
+    #[test]
+    fn it_samples_addresses_within_cidrs() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let cidr = Cidr::parse("192.168.10.0/24").unwrap();
+        let v6 = Cidr::parse("2001:db8:abcd::/48").unwrap();
+
+        for _ in 0..100 {
+            assert!(cidr.sample(&mut rng).to_string().starts_with("192.168.10."));
+            assert!(v6
+                .sample(&mut rng)
+                .to_string()
+                .starts_with("2001:db8:abcd:"));
+        }
+
+        assert_eq!(
+            Cidr::parse("10.1.2.3")
+                .unwrap()
+                .sample(&mut rng)
+                .to_string(),
+            "10.1.2.3"
+        );
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("example.com/8").is_err());
+    }
+
+    #[test]
+    fn it_keeps_public_addresses_out_of_private_and_reserved_ranges() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let excluded = [parse_all(&PRIVATE_V4), parse_all(&RESERVED_V4)].concat();
+        let public = Space {
+            ranges: parse_all(&["0.0.0.0/0"]),
+            excluded: excluded.clone(),
+        };
+
+        for _ in 0..1000 {
+            let address = public.sample(&mut rng).unwrap();
+
+            assert!(!excluded.iter().any(|cidr| cidr.contains(address)));
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use rand::{prelude::Distribution, Rng};
+use rand_distr::{Exp, LogNormal, Normal, Poisson, Zipf};
+use tera::{Result, Value};
+
+use super::{float_arg, int_arg};
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+};
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("randomint", "Random integer, both bounds inclusive")
+            .with_default("min", ParamType::Integer, 0, "Lower bound")
+            .optional(
+                "max",
+                ParamType::Integer,
+                "Upper bound, required unless 'range' is given",
+            )
+            .optional(
+                "range",
+                ParamType::Integer,
+                "Legacy exclusive upper bound, eg. range=10 rolls between 0 and 9",
+            )
+            .example("{{ randomint(min=1, max=6) }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let min = int_arg(args, "randomint", "min")?.unwrap_or(0);
+
+            let max = match (
+                int_arg(args, "randomint", "max")?,
+                int_arg(args, "randomint", "range")?,
+            ) {
+                (Some(max), _) => max,
+                (None, Some(range)) => range - 1,
+                (None, None) => {
+                    return Err("randomint: missing 'max' (or 'range') parameter".into())
+                }
+            };
+
+            if min > max {
+                return Err("randomint: 'max' must not be lower than 'min'".into());
+            }
+
+            Ok(rng.lock().unwrap().gen_range(min..=max).into())
+        },
+    );
+
+    // Overrides the tera builtin, which always draws from the thread rng
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("get_random", "Random integer, as the tera builtin")
+            .with_default("start", ParamType::Integer, 0, "Lower bound, inclusive")
+            .required("end", ParamType::Integer, "Upper bound, exclusive")
+            .example("{{ get_random(end=10) }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let start = int_arg(args, "get_random", "start")?.unwrap_or(0);
+            let end =
+                int_arg(args, "get_random", "end")?.ok_or("get_random: missing 'end' parameter")?;
+
+            if start >= end {
+                return Err("get_random: 'end' must be greater than 'start'".into());
+            }
+
+            Ok(rng.lock().unwrap().gen_range(start..end).into())
+        },
+    );
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        GeneratorSchema::new("randomfloat", "Random float")
+            .with_default("min", ParamType::Number, 0, "Lower bound, inclusive")
+            .with_default("max", ParamType::Number, 1, "Upper bound, exclusive")
+            .optional(
+                "precision",
+                ParamType::Integer,
+                "Number of decimal places to round to, 0 giving integers",
+            )
+            .example("{{ randomfloat(min=0, max=100, precision=2) }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let min = float_arg(args, "randomfloat", "min")?.unwrap_or(0.0);
+            let max = float_arg(args, "randomfloat", "max")?.unwrap_or(1.0);
+
+            if min >= max {
+                return Err("randomfloat: 'max' must be greater than 'min'".into());
+            }
+
+            let value = rng.lock().unwrap().gen_range(min..max);
+            let precision = int_arg(args, "randomfloat", "precision")?;
+
+            Ok(finish(value, Some(min), Some(max), precision, true))
+        },
+    );
+
+    register_distribution(
+        renderer,
+        GeneratorSchema::new("normal", "Normally distributed float")
+            .with_default("mean", ParamType::Number, 0, "Mean")
+            .with_default("std_dev", ParamType::Number, 1, "Standard deviation")
+            .example("{{ normal(mean=100, std_dev=15) }}"),
+        false,
+        |args| {
+            let mean = float_arg(args, "normal", "mean")?.unwrap_or(0.0);
+            let std_dev = float_arg(args, "normal", "std_dev")?.unwrap_or(1.0);
+
+            Normal::new(mean, std_dev)
+                .map_err(|_| "normal: 'std_dev' must be a finite, non-negative number".into())
+        },
+    );
+
+    register_distribution(
+        renderer,
+        GeneratorSchema::new(
+            "lognormal",
+            "Log-normally distributed float, eg. for durations or event sizes",
+        )
+        .with_default("mu", ParamType::Number, 0, "Mean of the logarithm")
+        .with_default(
+            "sigma",
+            ParamType::Number,
+            1,
+            "Standard deviation of the logarithm",
+        )
+        .example("{{ lognormal(mu=7, sigma=1.5) }}"),
+        false,
+        |args| {
+            let mu = float_arg(args, "lognormal", "mu")?.unwrap_or(0.0);
+            let sigma = float_arg(args, "lognormal", "sigma")?.unwrap_or(1.0);
+
+            LogNormal::new(mu, sigma)
+                .map_err(|_| "lognormal: 'sigma' must be a finite, non-negative number".into())
+        },
+    );
+
+    register_distribution(
+        renderer,
+        GeneratorSchema::new(
+            "exponential",
+            "Exponentially distributed float, eg. for intervals between events",
+        )
+        .with_default("lambda", ParamType::Number, 1, "Rate")
+        .example("{{ exponential(lambda=0.5) }}"),
+        false,
+        |args| {
+            let lambda = float_arg(args, "exponential", "lambda")?.unwrap_or(1.0);
+
+            Exp::new(lambda).map_err(|_| "exponential: 'lambda' must be positive".into())
+        },
+    );
+
+    register_distribution(
+        renderer,
+        GeneratorSchema::new(
+            "poisson",
+            "Poisson distributed integer, eg. for event counts",
+        )
+        .required("lambda", ParamType::Number, "Mean")
+        .example("{{ poisson(lambda=4) }}"),
+        true,
+        |args| {
+            let lambda = float_arg(args, "poisson", "lambda")?
+                .ok_or("poisson: missing 'lambda' parameter")?;
+
+            Poisson::new(lambda).map_err(|_| "poisson: 'lambda' must be positive".into())
+        },
+    );
+
+    register_distribution(
+        renderer,
+        GeneratorSchema::new(
+            "zipf",
+            "Zipf distributed integer: low values are frequent, high ones rare, eg. for ranks or popularity",
+        )
+        .required("n", ParamType::Integer, "Upper bound, the lower one being 1")
+        .with_default("s", ParamType::Number, 1, "Exponent")
+        .example("{{ zipf(n=1000, s=1.1) }}"),
+        true,
+        |args| {
+            let n = int_arg(args, "zipf", "n")?
+                .filter(|n| *n > 0)
+                .ok_or("zipf: 'n' must be a positive integer")?;
+            let s = float_arg(args, "zipf", "s")?.unwrap_or(1.0);
+
+            Zipf::new(n as u64, s).map_err(|_| "zipf: 's' must be non-negative".into())
+        },
+    );
+}
+
+/// Registers a generator sampling from a distribution built from the generator arguments. All of
+/// them accept optional 'min' and 'max' bounds, float ones a 'precision' too
+fn register_distribution<D, F>(
+    renderer: &mut DocumentRenderer,
+    schema: GeneratorSchema,
+    integer: bool,
+    build: F,
+) where
+    D: Distribution<f64>,
+    F: Fn(&HashMap<String, Value>) -> Result<D> + Send + Sync + 'static,
+{
+    let mut schema = schema
+        .optional("min", ParamType::Number, "Values below are clamped to it")
+        .optional("max", ParamType::Number, "Values above are clamped to it");
+
+    if !integer {
+        schema = schema.optional(
+            "precision",
+            ParamType::Integer,
+            "Number of decimal places to round to, 0 giving integers",
+        );
+    }
+
+    let name = schema.name.clone();
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        schema,
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let distribution = build(args)?;
+            let value = distribution.sample(&mut *rng.lock().unwrap());
+
+            let min = float_arg(args, &name, "min")?;
+            let max = float_arg(args, &name, "max")?;
+            let precision = if integer {
+                Some(0)
+            } else {
+                int_arg(args, &name, "precision")?
+            };
+
+            Ok(finish(value, min, max, precision, false))
+        },
+    );
+}
+
+/// Applies the resolved 'min', 'max' and 'precision' arguments to a sampled value. Values are
+/// rounded before being clamped, so that rounding never moves them out of the bounds (`max` being
+/// excluded when `exclusive_max`), and are returned as integers when rounded to 0 decimal places
+fn finish(
+    value: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    precision: Option<i64>,
+    exclusive_max: bool,
+) -> Value {
+    // with a precision, values are multiples of 1 / factor
+    let factor = precision.map(|precision| 10f64.powi(precision.clamp(0, 15) as i32));
+
+    let mut value = match factor {
+        Some(factor) => (value * factor).round() / factor,
+        None => value,
+    };
+
+    if let Some(max) = max {
+        let above = |value: f64| value > max || (exclusive_max && value >= max);
+
+        if above(value) {
+            value = match factor {
+                Some(factor) => {
+                    let mut steps = (max * factor).floor();
+
+                    if above(steps / factor) {
+                        steps -= 1.0;
+                    }
+
+                    steps / factor
+                }
+                None => max,
+            };
+        }
+    }
+
+    if let Some(min) = min {
+        if value < min {
+            value = match factor {
+                Some(factor) => {
+                    let mut steps = (min * factor).ceil();
+
+                    if steps / factor < min {
+                        steps += 1.0;
+                    }
+
+                    steps / factor
+                }
+                None => min,
+            };
+        }
+    }
+
+    if precision == Some(0) {
+        return (value as i64).into();
+    }
+
+    value.into()
+}
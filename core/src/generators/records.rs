@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tera::{Result, Value};
+
+use super::str_arg;
+use crate::{
+    document_renderer::{DocumentRenderer, SharedRecords},
+    generator_schema::{GeneratorSchema, ParamType},
+};
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    let datasets = renderer.datasets();
+    register_records(
+        renderer,
+        GeneratorSchema::new(
+            "dataset",
+            "Row of a dataset declared under 'datasets', or one of its columns",
+        )
+        .required("name", ParamType::String, "Name of the dataset")
+        .optional(
+            "field",
+            ParamType::String,
+            "Column to return, the whole row by default",
+        )
+        .optional(
+            "key",
+            ParamType::Any,
+            "Entity identifier, always getting the same row, eg. doc.seq % 20",
+        )
+        .example("{{ dataset(name='employees', key=doc.seq % 20, field='email') }}"),
+        "name",
+        datasets,
+    );
+
+    let entities = renderer.entities();
+    register_records(
+        renderer,
+        GeneratorSchema::new(
+            "entity",
+            "Entity of a population declared under 'entities', or one of its attributes",
+        )
+        .required("type", ParamType::String, "Name of the entity population")
+        .optional(
+            "field",
+            ParamType::String,
+            "Attribute to return, the whole entity by default",
+        )
+        .optional(
+            "key",
+            ParamType::Any,
+            "Identifier always getting the same entity, eg. a session id",
+        )
+        .example("{{ entity(type='host', key=doc.seq % 5, field='ip') }}"),
+        "type",
+        entities,
+    );
+}
+
+/// Registers a generator drawing rows of `records`, the dataset or population being named by
+/// its `param` argument
+fn register_records(
+    renderer: &mut DocumentRenderer,
+    schema: GeneratorSchema,
+    param: &'static str,
+    records: SharedRecords,
+) {
+    let generator = schema.name.clone();
+
+    // rows already given to keys, so that a key keeps its row for the whole run
+    let rows: Arc<Mutex<HashMap<(String, String), Value>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let rng = renderer.rng();
+    renderer.register_generator(
+        schema,
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let name = str_arg(args, &generator, param)?
+                .ok_or_else(|| format!("{}: missing '{}' parameter", generator, param))?;
+            let field = str_arg(args, &generator, "field")?;
+
+            let key = args.get("key").map(|key| match key {
+                Value::String(key) => (name.to_owned(), key.clone()),
+                key => (name.to_owned(), key.to_string()),
+            });
+
+            let known = key
+                .as_ref()
+                .and_then(|key| rows.lock().unwrap().get(key).cloned());
+
+            let row = match known {
+                Some(row) => row,
+                None => {
+                    let row = records
+                        .read()
+                        .unwrap()
+                        .get(name)
+                        .ok_or_else(|| format!("{}: unknown {} '{}'", generator, generator, name))?
+                        .draw(&mut *rng.lock().unwrap())
+                        .clone();
+
+                    if let Some(key) = key {
+                        rows.lock().unwrap().insert(key, row.clone());
+                    }
+
+                    row
+                }
+            };
+
+            match field {
+                Some(field) => row.get(field).cloned().ok_or_else(|| {
+                    format!(
+                        "{}: {} '{}' has no field '{}'",
+                        generator, generator, name, field
+                    )
+                    .into()
+                }),
+                None => Ok(row),
+            }
+        },
+    );
+}
@@ -0,0 +1,64 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tera::{Result, Value};
+
+use super::{int_arg, str_arg};
+use crate::{
+    document_renderer::DocumentRenderer,
+    generator_schema::{GeneratorSchema, ParamType},
+};
+
+pub(crate) fn register(renderer: &mut DocumentRenderer) {
+    // next value of every counter, kept by the renderer for the whole run, across batches and
+    // fixtures
+    let counters: Arc<Mutex<HashMap<String, i64>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    renderer.register_generator(
+        GeneratorSchema::new(
+            "sequence",
+            "Counter increasing on every call, shared by the calls with the same name",
+        )
+        .with_default("name", ParamType::String, "default", "Counter to draw from")
+        .with_default(
+            "start",
+            ParamType::Integer,
+            1,
+            "First value, read on the first call",
+        )
+        .with_default("step", ParamType::Integer, 1, "Increment, may be negative")
+        .optional(
+            "pad",
+            ParamType::Integer,
+            "Width to zero-pad the value to, returning a string",
+        )
+        .example("{{ sequence(name='orders', start=1000, pad=8) }}"),
+        move |args: &HashMap<String, Value>| -> Result<Value> {
+            let name = str_arg(args, "sequence", "name")?.unwrap_or("default");
+            let start = int_arg(args, "sequence", "start")?.unwrap_or(1);
+            let step = int_arg(args, "sequence", "step")?.unwrap_or(1);
+
+            let pad = match int_arg(args, "sequence", "pad")? {
+                Some(pad) if pad < 0 => {
+                    return Err("sequence: 'pad' must not be negative".into());
+                }
+                pad => pad,
+            };
+
+            let mut counters = counters.lock().unwrap();
+            let counter = counters.entry(name.to_owned()).or_insert(start);
+
+            let value = *counter;
+            *counter = value
+                .checked_add(step)
+                .ok_or_else(|| format!("sequence: counter '{}' overflowed", name))?;
+
+            match pad {
+                Some(pad) => Ok(format!("{:0width$}", value, width = pad as usize).into()),
+                None => Ok(value.into()),
+            }
+        },
+    );
+}
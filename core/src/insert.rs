@@ -1,59 +1,213 @@
 use anyhow::Ok;
-use elasticsearch::{
-    http::{request::JsonBody, response::Response},
-    BulkParts, Elasticsearch,
-};
+use elasticsearch::{http::request::JsonBody, BulkParts, Elasticsearch};
 
 use anyhow::Result;
 use serde_json::json;
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    document_context::DocumentContext, document_renderer::DocumentRenderer,
+    document_template::DocumentTemplate, insert_report::InsertReport, pipelines::Pipeline,
+    scenario::Scenario,
+};
 
-use crate::document_renderer::DocumentRenderer;
+/// Number of ids generated so far, telling apart ids generated within the same clock tick
+static GENERATED_IDS: AtomicU64 = AtomicU64::new(0);
 
 // This is temporary until id's are optional
 fn generate_id() -> String {
     let start = SystemTime::now();
-    let timestamp = start
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos()
-        .to_string();
+    let timestamp = start.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+
+    format!(
+        "{}-{}",
+        timestamp,
+        GENERATED_IDS.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Bulk action writing the documents
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpType {
+    /// `index` action with a generated id, for regular indices
+    Index,
+    /// `create` action, the only one data streams accept
+    Create,
+}
+
+impl OpType {
+    fn action(&self) -> serde_json::Value {
+        match self {
+            OpType::Index => json!({"index": {"_id": generate_id().as_str()}}),
+            OpType::Create => json!({"create": {}}),
+        }
+    }
+}
+
+/// How the documents of a fixture are written
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkOptions {
+    pub op_type: OpType,
+    /// Ingest pipeline the documents go through
+    pub pipeline: Option<String>,
+}
+
+impl BulkOptions {
+    /// Templates declaring an `index_template` write to a data stream, and templates declaring a
+    /// `pipeline` go through it
+    pub fn for_template(template: &serde_json::Value, index: &str) -> Result<Self> {
+        let op_type = match template.get("index_template") {
+            Some(_) => OpType::Create,
+            None => OpType::Index,
+        };
+
+        let pipeline = Pipeline::from_template(template, index)?.map(|pipeline| pipeline.name);
 
-    timestamp
+        Ok(Self { op_type, pipeline })
+    }
 }
 
-/// Insert documents in bulk
-pub async fn insert_batch<'a>(
+/// Insert documents in bulk into the index of `document`, which describes the first document of
+/// the batch. The outcome of every document is recorded into `report`
+pub async fn insert_batch(
     client: &Elasticsearch,
-    index: &str,
-    document_template: Option<&'a serde_json::Value>,
+    document_template: &DocumentTemplate,
+    mut document: DocumentContext<'_>,
     batch_size: usize,
-    renderer: &mut DocumentRenderer,
-) -> Result<Response> {
-    let mut bulk_operations: Vec<JsonBody<serde_json::Value>> = Vec::with_capacity(batch_size * 2);
+    renderer: &DocumentRenderer,
+    options: &BulkOptions,
+    report: &mut InsertReport,
+) -> Result<()> {
+    let index = document.index;
+    let mut documents = Vec::with_capacity(batch_size);
 
     for _ in 0..batch_size {
-        // read document from file
-        bulk_operations.push(json!({"index": {"_id": generate_id().as_str()}}).into());
+        // compiled tera template for a document to insert
+        documents.push(renderer.render_document(document_template, &document)?);
+
+        document.advance();
+    }
+
+    bulk_index(client, index, documents, options, report).await
+}
+
+/// Insert whole scenario sequences in bulk, `document` describes the first document of the batch
+pub async fn insert_sequences(
+    client: &Elasticsearch,
+    scenario: &Scenario,
+    mut document: DocumentContext<'_>,
+    sequences: usize,
+    renderer: &DocumentRenderer,
+    options: &BulkOptions,
+    report: &mut InsertReport,
+) -> Result<()> {
+    let index = document.index;
+    let mut documents = Vec::with_capacity(sequences * scenario.steps.len());
+
+    for _ in 0..sequences {
+        documents.extend(renderer.render_sequence(scenario, &mut document)?);
+    }
+
+    bulk_index(client, index, documents, options, report).await
+}
+
+async fn bulk_index(
+    client: &Elasticsearch,
+    index: &str,
+    documents: Vec<serde_json::Value>,
+    options: &BulkOptions,
+    report: &mut InsertReport,
+) -> Result<()> {
+    let actions = bulk_actions(options.op_type, documents.len());
+
+    // the documents are borrowed, so that failed ones can be reported
+    let mut bulk_operations: Vec<JsonBody<&serde_json::Value>> =
+        Vec::with_capacity(documents.len() * 2);
+
+    for (action, rendered_document) in actions.iter().zip(&documents) {
+        bulk_operations.push(action.into());
+        bulk_operations.push(rendered_document.into());
+    }
+
+    let request = client.bulk(BulkParts::Index(index)).body(bulk_operations);
+
+    let request = match &options.pipeline {
+        Some(pipeline) => request.pipeline(pipeline),
+        None => request,
+    };
+
+    let response = request.send().await?;
 
-        // handlebars template for a document to insert
-        let document_template_string = document_template
-            .ok_or(anyhow::anyhow!("missing template"))?
-            .to_string();
+    let status = response.status_code();
 
-        let rendered_document = renderer.render(&document_template_string).unwrap();
+    // without items, the outcome of the documents is unknown, they all count as failed
+    let body: serde_json::Value = response.json().await.unwrap_or_else(
+        |err| json!({ "error": { "type": "invalid_bulk_response", "reason": err.to_string() } }),
+    );
 
-        let parsed_document_json: serde_json::Value = serde_json::from_str(&rendered_document)?;
+    if status == 200 && body.get("error").is_none() {
+        report.record_bulk(index, &body, &documents);
+    } else {
+        report.record_failed_request(index, status.as_u16(), &body, &documents);
+    }
+
+    Ok(())
+}
+
+/// One bulk action per document, as `index` actions carry the id of their document
+fn bulk_actions(op_type: OpType, documents: usize) -> Vec<serde_json::Value> {
+    (0..documents).map(|_| op_type.action()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
 
-        bulk_operations.push(parsed_document_json.into());
+    use serde_json::json;
+
+    use super::{bulk_actions, BulkOptions, OpType};
+
+    #[test]
+    fn it_gives_every_document_of_a_batch_its_own_id() {
+        let ids: HashSet<String> = bulk_actions(OpType::Index, 1000)
+            .iter()
+            .map(|action| action["index"]["_id"].as_str().unwrap().to_owned())
+            .collect();
+
+        assert_eq!(ids.len(), 1000);
+
+        assert_eq!(
+            bulk_actions(OpType::Create, 2),
+            vec![json!({ "create": {} }); 2]
+        );
     }
 
-    let response = client
-        .bulk(BulkParts::Index(index))
-        .body(bulk_operations)
-        .send()
-        .await?;
+    #[test]
+    fn it_picks_bulk_options_from_templates() {
+        assert_eq!(
+            BulkOptions::for_template(&json!({ "index": {} }), "logs").unwrap(),
+            BulkOptions {
+                op_type: OpType::Index,
+                pipeline: None
+            }
+        );
+
+        assert_eq!(
+            BulkOptions::for_template(
+                &json!({ "index_template": {}, "pipeline": { "processors": [] } }),
+                "logs-fakebeat-default"
+            )
+            .unwrap(),
+            BulkOptions {
+                op_type: OpType::Create,
+                pipeline: Some("logs-fakebeat-default-pipeline".to_owned())
+            }
+        );
 
-    Ok(response)
+        assert!(BulkOptions::for_template(&json!({ "pipeline": 1 }), "logs").is_err());
+    }
 }
@@ -1,24 +1,79 @@
-use crate::{document_renderer, insert::insert_batch};
+use crate::{
+    declarations::load_fixture_declarations,
+    document_context::DocumentContext,
+    document_renderer::DocumentRenderer,
+    insert::{insert_batch, insert_sequences, BulkOptions},
+    insert_report::InsertReport,
+};
 use anyhow::Result;
 use elasticsearch::Elasticsearch;
 use tokio::fs::read_to_string;
 
 use crate::fixture::Fixture;
 
+/// Inserts the documents of every fixture. `on_progress` receives the number of fixture units
+/// inserted so far: documents, or whole sequences for scenario fixtures. Returns how many
+/// documents were inserted and why the others failed
 pub async fn insert_fixtures<'a>(
     client: &'a Elasticsearch,
     fixtures: &'a Vec<Fixture>,
     batch_size: usize,
+    renderer: &mut DocumentRenderer,
     mut on_progress: Box<dyn FnMut(usize) -> ()>,
-) -> Result<()> {
+) -> Result<InsertReport> {
+    let mut report = InsertReport::default();
     let mut total_generated: usize = 0;
+    let mut total_documents: usize = 0;
 
-    let mut renderer = document_renderer::DocumentRendererFactory::create_renderer();
+    load_fixture_declarations(renderer, fixtures).await?;
 
     for fixture in fixtures.iter() {
         let template_file = read_to_string(&fixture.template).await?;
         let template: serde_json::Value = serde_json::from_str(&template_file)?;
-        let values_definition = template.get("values");
+
+        let options = BulkOptions::for_template(&template, &fixture.index)?;
+
+        if template.get("scenario").is_some() {
+            let scenario = renderer.compile_scenario(&template)?;
+            let steps = scenario.steps.len();
+
+            // the count of a scenario fixture is a number of sequences, batches keep them whole
+            let sequences_per_batch = (batch_size / steps).max(1);
+            let mut local_to_generate = fixture.count;
+
+            while local_to_generate > 0 {
+                let sequences = sequences_per_batch.min(local_to_generate);
+
+                let document = DocumentContext {
+                    seq: (fixture.count - local_to_generate) * steps,
+                    global_seq: total_documents,
+                    index: &fixture.index,
+                    count: fixture.count * steps,
+                };
+
+                insert_sequences(
+                    client,
+                    &scenario,
+                    document,
+                    sequences,
+                    renderer,
+                    &options,
+                    &mut report,
+                )
+                .await?;
+
+                local_to_generate -= sequences;
+
+                total_documents += sequences * steps;
+                total_generated += sequences;
+
+                on_progress(total_generated);
+            }
+
+            continue;
+        }
+
+        let document_template = renderer.compile_template(&template)?;
 
         let mut local_to_generate = fixture.count;
 
@@ -29,34 +84,32 @@ pub async fn insert_fixtures<'a>(
                 batch_size
             };
 
-            let insertion_result = insert_batch(
+            let document = DocumentContext {
+                seq: fixture.count - local_to_generate,
+                global_seq: total_documents,
+                index: &fixture.index,
+                count: fixture.count,
+            };
+
+            insert_batch(
                 &client,
-                &fixture.index,
-                values_definition,
+                &document_template,
+                document,
                 batch_size,
-                &mut renderer,
+                renderer,
+                &options,
+                &mut report,
             )
             .await?;
 
-            if insertion_result.status_code() != 200 {
-                eprintln!(
-                    "could not insert documents into index {};
-                    request failed with status: {} (using template file: {})",
-                    &fixture.index,
-                    insertion_result.status_code(),
-                    &fixture.template
-                );
-
-                dbg!(insertion_result);
-            }
-
             local_to_generate -= batch_size;
 
+            total_documents += batch_size;
             total_generated += batch_size;
 
             on_progress(total_generated);
         }
     }
 
-    Ok(())
+    Ok(report)
 }
@@ -0,0 +1,206 @@
+use std::{cmp::Reverse, collections::BTreeMap, fmt};
+
+use serde_json::{json, Value};
+
+/// Documents rejected for the same error type in the same index
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureGroup {
+    pub error_type: String,
+    pub index: String,
+    pub count: usize,
+    /// Reason given for the first failure of the group
+    pub reason: String,
+    /// First failed document of the group
+    pub sample: Value,
+}
+
+/// Outcome of the bulk requests of a run: how many documents were inserted, and why the others
+/// were not
+#[derive(Debug, Default)]
+pub struct InsertReport {
+    inserted: usize,
+    failed: usize,
+    groups: BTreeMap<(String, String), FailureGroup>,
+}
+
+impl InsertReport {
+    /// Accounts for the items of a successful bulk response, `documents` being the documents
+    /// sent, in the same order. Documents without an item in the response count as failed
+    pub fn record_bulk(&mut self, index: &str, body: &Value, documents: &[Value]) {
+        let items = body["items"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+
+        let missing = json!({
+            "type": "missing_bulk_item",
+            "reason": "the bulk response has no item for the document",
+        });
+
+        for (i, document) in documents.iter().enumerate() {
+            // every item holds the outcome of its action, eg. {"create": {"status": 201, ...}}
+            let outcome = match items.get(i).and_then(Value::as_object) {
+                Some(item) => item.values().next().unwrap_or(&Value::Null),
+                None => {
+                    self.record_failure(index, &missing, document);
+                    continue;
+                }
+            };
+
+            match outcome.get("error") {
+                Some(error) => {
+                    let index = outcome["_index"].as_str().unwrap_or(index);
+
+                    self.record_failure(index, error, document);
+                }
+                None => self.inserted += 1,
+            }
+        }
+    }
+
+    /// Accounts for a bulk request rejected as a whole, eg. with a 413 or 401 status
+    pub fn record_failed_request(
+        &mut self,
+        index: &str,
+        status: u16,
+        body: &Value,
+        documents: &[Value],
+    ) {
+        let error = match body.get("error") {
+            Some(Value::Object(error)) => Value::Object(error.clone()),
+            Some(Value::String(reason)) => json!({ "reason": reason }),
+            _ => Value::Null,
+        };
+
+        let error = match error["type"].as_str() {
+            Some(_) => error,
+            None => json!({
+                "type": format!("http_status_{}", status),
+                "reason": error["reason"].as_str().unwrap_or("bulk request failed"),
+            }),
+        };
+
+        for document in documents {
+            self.record_failure(index, &error, document);
+        }
+    }
+
+    fn record_failure(&mut self, index: &str, error: &Value, document: &Value) {
+        let error_type = error["type"].as_str().unwrap_or("unknown").to_owned();
+
+        self.failed += 1;
+
+        self.groups
+            .entry((error_type.clone(), index.to_owned()))
+            .or_insert_with(|| FailureGroup {
+                error_type,
+                index: index.to_owned(),
+                count: 0,
+                reason: error["reason"].as_str().unwrap_or_default().to_owned(),
+                sample: document.clone(),
+            })
+            .count += 1;
+    }
+
+    pub fn inserted(&self) -> usize {
+        self.inserted
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Groups of failures, the largest first
+    pub fn groups(&self) -> Vec<&FailureGroup> {
+        let mut groups: Vec<&FailureGroup> = self.groups.values().collect();
+        groups.sort_by_key(|group| Reverse(group.count));
+
+        groups
+    }
+}
+
+impl fmt::Display for InsertReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Inserted {} documents, {} failed",
+            self.inserted, self.failed
+        )?;
+
+        for group in self.groups() {
+            write!(
+                f,
+                "\n  {} in {}: {} documents\n    reason: {}\n    sample: {}",
+                group.error_type, group.index, group.count, group.reason, group.sample
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::InsertReport;
+
+    #[test]
+    fn it_groups_failures_by_error_type_and_index() {
+        let mut report = InsertReport::default();
+
+        let mapping_error = |port: &str| {
+            json!({ "index": { "_index": "logs", "status": 400, "error": {
+                "type": "mapper_parsing_exception",
+                "reason": format!("failed to parse field [source.port] with value [{}]", port)
+            } } })
+        };
+
+        report.record_bulk(
+            "logs",
+            &json!({
+                "errors": true,
+                "items": [
+                    { "index": { "_index": "logs", "status": 201 } },
+                    mapping_error("http"),
+                    mapping_error("ssh"),
+                    { "index": { "_index": "logs", "status": 201 } }
+                ]
+            }),
+            &[
+                json!({ "source.port": 80 }),
+                json!({ "source.port": "http" }),
+                json!({ "source.port": "ssh" }),
+                json!({ "source.port": 22 }),
+            ],
+        );
+
+        report.record_failed_request("metrics", 413, &json!(null), &[json!({ "value": 1 })]);
+
+        assert_eq!(report.inserted(), 2);
+        assert_eq!(report.failed(), 3);
+
+        // a response without an item for every document
+        report.record_bulk(
+            "logs",
+            &json!({ "errors": false, "items": [{ "index": { "_index": "logs", "status": 201 } }] }),
+            &[json!({ "source.port": 443 }), json!({ "source.port": 8443 })],
+        );
+        report.record_bulk("logs", &json!(null), &[json!({ "source.port": 25 })]);
+
+        assert_eq!(report.inserted(), 3);
+        assert_eq!(report.failed(), 5);
+
+        let groups = report.groups();
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].error_type, "mapper_parsing_exception");
+        assert_eq!(groups[0].count, 2);
+        assert_eq!(groups[0].sample, json!({ "source.port": "http" }));
+        assert_eq!(groups[1].error_type, "missing_bulk_item");
+        assert_eq!(groups[1].sample, json!({ "source.port": 8443 }));
+        assert_eq!(groups[2].error_type, "http_status_413");
+        assert_eq!(groups[2].index, "metrics");
+
+        assert!(report
+            .to_string()
+            .starts_with("Inserted 3 documents, 5 failed\n  mapper_parsing_exception in logs"));
+    }
+}
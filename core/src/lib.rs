@@ -1,10 +1,24 @@
 pub mod fixture;
+pub mod dataset;
+pub mod declarations;
+pub mod dictionary;
+pub mod document_context;
+pub mod document_template;
 pub mod ensure_index;
 pub mod insert_fixtures;
 pub mod document_renderer;
+pub mod generator_schema;
+mod generators;
 pub mod insert;
+pub mod insert_report;
 pub mod local_esclient;
+pub mod locale;
+pub mod pipelines;
+pub mod pool;
 pub mod prepare_indices;
+pub mod render_plan;
+pub mod scenario;
+pub mod time_model;
 
 // use proc_macro::TokenStream;
 
@@ -0,0 +1,70 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+
+/// Locales of the faker-backed generators, as provided by the `fake` crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    FrFr,
+    ZhCn,
+    ZhTw,
+    JaJp,
+    PtBr,
+    ArSa,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 7] = [
+        Locale::En,
+        Locale::FrFr,
+        Locale::ZhCn,
+        Locale::ZhTw,
+        Locale::JaJp,
+        Locale::PtBr,
+        Locale::ArSa,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::FrFr => "fr_FR",
+            Locale::ZhCn => "zh_CN",
+            Locale::ZhTw => "zh_TW",
+            Locale::JaJp => "ja_JP",
+            Locale::PtBr => "pt_BR",
+            Locale::ArSa => "ar_SA",
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    /// Accepts names like `fr_FR`, `fr-fr` or `en_US` (any english variant maps to `en`)
+    fn from_str(locale: &str) -> Result<Self, Self::Err> {
+        let normalized = locale.trim().replace('-', "_").to_lowercase();
+
+        if normalized == "en" || normalized.starts_with("en_") {
+            return Ok(Locale::En);
+        }
+
+        Locale::ALL
+            .into_iter()
+            .find(|candidate| candidate.name().to_lowercase() == normalized)
+            .ok_or_else(|| {
+                anyhow!(
+                    "unsupported locale \"{}\", expected one of: {}",
+                    locale,
+                    Locale::ALL.map(|locale| locale.name()).join(", ")
+                )
+            })
+    }
+}
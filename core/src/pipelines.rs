@@ -0,0 +1,328 @@
+use anyhow::{anyhow, Context, Result};
+use elasticsearch::{
+    ingest::{IngestPutPipelineParts, IngestSimulateParts},
+    Elasticsearch,
+};
+use serde_json::{json, Value};
+use tokio::fs::read_to_string;
+
+use crate::{
+    declarations::{load_fixture_declarations, RendererSetup},
+    document_context::DocumentContext,
+    ensure_index::check,
+    fixture::Fixture,
+};
+
+/// Ingest pipeline the documents of a fixture go through, declared in its template as
+/// `"pipeline": "name"` for an existing pipeline, or as
+/// `"pipeline": {"name": ..., "processors": [...]}` for one installed by fakebeat
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub name: String,
+    /// Body of the put pipeline request, for pipelines to install
+    pub definition: Option<Value>,
+}
+
+impl Pipeline {
+    /// Reads the pipeline of a template, installed pipelines are named after the index unless
+    /// they have a `name`
+    pub fn from_template(template: &Value, index: &str) -> Result<Option<Self>> {
+        let pipeline = match template.get("pipeline") {
+            Some(pipeline) => pipeline,
+            None => return Ok(None),
+        };
+
+        match pipeline {
+            Value::String(name) => Ok(Some(Self {
+                name: name.to_owned(),
+                definition: None,
+            })),
+            Value::Object(definition) => {
+                let mut definition = definition.clone();
+
+                let name = match definition.remove("name") {
+                    Some(Value::String(name)) => name,
+                    Some(_) => return Err(anyhow!("pipeline name should be a string")),
+                    None => format!("{}-pipeline", index),
+                };
+
+                Ok(Some(Self {
+                    name,
+                    definition: Some(Value::Object(definition)),
+                }))
+            }
+            _ => Err(anyhow!(
+                "pipeline should be the name of an existing pipeline or a pipeline definition, check your template"
+            )),
+        }
+    }
+}
+
+async fn read_pipeline(fixture: &Fixture) -> Result<(Value, Option<Pipeline>)> {
+    let template_file = read_to_string(&fixture.template).await?;
+    let template: Value = serde_json::from_str(&template_file)?;
+
+    let pipeline = Pipeline::from_template(&template, &fixture.index)
+        .with_context(|| format!("invalid pipeline in {}", fixture.template))?;
+
+    Ok((template, pipeline))
+}
+
+/// Puts the pipelines defined in the fixture templates, replacing existing ones
+pub async fn install_pipelines(client: &Elasticsearch, fixtures: &[Fixture]) -> Result<()> {
+    for fixture in fixtures {
+        let (_, pipeline) = read_pipeline(fixture).await?;
+
+        let (name, definition) = match pipeline {
+            Some(Pipeline {
+                name,
+                definition: Some(definition),
+            }) => (name, definition),
+            _ => continue,
+        };
+
+        println!("Putting ingest pipeline \"{}\"", name);
+
+        let response = client
+            .ingest()
+            .put_pipeline(IngestPutPipelineParts::Id(&name))
+            .body(definition)
+            .send()
+            .await?;
+
+        check(response, &format!("put ingest pipeline \"{}\"", name)).await?;
+    }
+
+    Ok(())
+}
+
+/// Renders a sample of every fixture going through a pipeline: one document, or one sequence for
+/// scenarios. The samples get a renderer of their own created from `setup`, so that rendering
+/// them changes nothing in the documents of the run.
+pub async fn render_samples<'a>(
+    setup: &RendererSetup,
+    fixtures: &'a [Fixture],
+) -> Result<Vec<(&'a Fixture, Pipeline, Vec<Value>)>> {
+    let mut renderer = setup.create_renderer().await?;
+
+    load_fixture_declarations(&mut renderer, fixtures).await?;
+
+    let mut samples = Vec::new();
+
+    for fixture in fixtures {
+        let (template, pipeline) = read_pipeline(fixture).await?;
+
+        let pipeline = match pipeline {
+            Some(pipeline) => pipeline,
+            None => continue,
+        };
+
+        let mut document = DocumentContext {
+            seq: 0,
+            global_seq: 0,
+            index: &fixture.index,
+            count: fixture.count,
+        };
+
+        let sample = if template.get("scenario").is_some() {
+            let scenario = renderer.compile_scenario(&template)?;
+            document.count *= scenario.steps.len();
+
+            renderer.render_sequence(&scenario, &mut document)?
+        } else {
+            let document_template = renderer.compile_template(&template)?;
+
+            vec![renderer.render_document(&document_template, &document)?]
+        };
+
+        samples.push((fixture, pipeline, sample));
+    }
+
+    Ok(samples)
+}
+
+/// Runs the pipeline of every fixture over a sample rendered from its template (see
+/// [`render_samples`]) and prints the outcome. Fails if the pipeline fails on any sample
+/// document.
+pub async fn simulate_pipelines(
+    client: &Elasticsearch,
+    fixtures: &[Fixture],
+    setup: &RendererSetup,
+) -> Result<()> {
+    let mut failures = 0;
+
+    for (fixture, pipeline, sample) in render_samples(setup, fixtures).await? {
+        let docs: Vec<Value> = sample
+            .into_iter()
+            .map(|source| json!({ "_index": fixture.index, "_source": source }))
+            .collect();
+
+        let response = client
+            .ingest()
+            .simulate(IngestSimulateParts::Id(&pipeline.name))
+            .body(json!({ "docs": docs }))
+            .send()
+            .await?;
+
+        let status = response.status_code();
+
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+
+            return Err(anyhow!(
+                "could not simulate ingest pipeline \"{}\": {} {}",
+                pipeline.name,
+                status,
+                body
+            ));
+        }
+
+        let result: Value = response.json().await?;
+
+        println!(
+            "Simulated ingest pipeline \"{}\" over a sample of {}:",
+            pipeline.name, fixture.template
+        );
+
+        for doc in result["docs"].as_array().into_iter().flatten() {
+            match doc.get("error") {
+                Some(error) => {
+                    failures += 1;
+
+                    eprintln!(
+                        "  failed: {}: {}",
+                        error["type"].as_str().unwrap_or("unknown"),
+                        error["reason"].as_str().unwrap_or_default()
+                    );
+                }
+                None => println!("{}", serde_json::to_string_pretty(&doc["doc"]["_source"])?),
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(anyhow!(
+            "ingest pipelines failed on {} sample documents",
+            failures
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{render_samples, Pipeline};
+    use crate::{
+        declarations::{load_fixture_declarations, RendererSetup},
+        document_context::DocumentContext,
+        fixture::Fixture,
+    };
+
+    #[test]
+    fn it_reads_existing_and_installed_pipelines() {
+        assert_eq!(
+            Pipeline::from_template(&json!({ "pipeline": "geoip" }), "logs").unwrap(),
+            Some(Pipeline {
+                name: "geoip".to_owned(),
+                definition: None
+            })
+        );
+
+        let installed = Pipeline::from_template(
+            &json!({ "pipeline": { "processors": [{ "geoip": { "field": "source.ip" } }] } }),
+            "logs",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(installed.name, "logs-pipeline");
+        assert_eq!(
+            installed.definition,
+            Some(json!({ "processors": [{ "geoip": { "field": "source.ip" } }] }))
+        );
+
+        assert_eq!(Pipeline::from_template(&json!({}), "logs").unwrap(), None);
+        assert!(Pipeline::from_template(&json!({ "pipeline": 1 }), "logs").is_err());
+    }
+
+    /// Documents of a run of `fixture`, rendered by a renderer created from `setup`
+    async fn render_run(setup: &RendererSetup, fixture: &Fixture) -> Vec<Value> {
+        let mut renderer = setup.create_renderer().await.unwrap();
+        load_fixture_declarations(&mut renderer, std::slice::from_ref(fixture))
+            .await
+            .unwrap();
+
+        let template = std::fs::read_to_string(&fixture.template).unwrap();
+        let template = renderer
+            .compile_template(&serde_json::from_str(&template).unwrap())
+            .unwrap();
+
+        let mut document = DocumentContext {
+            seq: 0,
+            global_seq: 0,
+            index: &fixture.index,
+            count: fixture.count,
+        };
+
+        (0..fixture.count)
+            .map(|_| {
+                let rendered = renderer.render_document(&template, &document).unwrap();
+                document.advance();
+
+                rendered
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn it_renders_samples_without_changing_the_run() {
+        let template = std::env::temp_dir().join(format!(
+            "fakebeat-pipeline-sample-{}.json",
+            std::process::id()
+        ));
+
+        std::fs::write(
+            &template,
+            json!({
+                "pipeline": "geoip",
+                "entities": { "host": { "size": 3, "attributes": { "name": "{{ uuid() }}" } } },
+                "values": {
+                    "seq": "{{ sequence() }}",
+                    "code": { "$unique": { "value": "{{ randomint(max=9) }}" } },
+                    "host": "{{ entity(type='host', key=doc.seq % 2, field='name') }}",
+                    "run": "{{ run.id }}"
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let fixture = Fixture {
+            index: "logs".to_owned(),
+            template: template.to_string_lossy().into_owned(),
+            count: 5,
+        };
+        let setup = RendererSetup {
+            seed: Some(42),
+            now: Some("2022-11-20T12:00:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+
+        let without_simulation = render_run(&setup, &fixture).await;
+
+        let samples = render_samples(&setup, std::slice::from_ref(&fixture))
+            .await
+            .unwrap();
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].1.name, "geoip");
+        assert_eq!(samples[0].2, vec![without_simulation[0].clone()]);
+
+        assert_eq!(render_run(&setup, &fixture).await, without_simulation);
+
+        std::fs::remove_file(template).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+use rand::{seq::SliceRandom, Rng};
+use serde_json::Value;
+
+/// Named set of values shared between fixtures, eg. the urls of threat indicators that events should match
+#[derive(Debug)]
+pub struct Pool {
+    /// Values of the pool
+    pub members: Vec<Value>,
+    /// Values generated from the same definition, guaranteed not to be pool members
+    pub decoys: Vec<Value>,
+}
+
+impl Pool {
+    /// Draws a value from the pool.
+    ///
+    /// Without `overlap` a member is returned, picked at `index` if given, randomly otherwise.
+    /// With `overlap` only that fraction of draws returns a member, the rest returns decoys. When
+    /// `index` is given too, hits are spread evenly, so that `n` consecutive indexes produce
+    /// exactly `floor(n * overlap)` members.
+    pub fn draw<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        index: Option<u64>,
+        overlap: Option<f64>,
+    ) -> Option<Value> {
+        let hit = match (overlap, index) {
+            (None, _) => true,
+            (Some(overlap), Some(index)) => {
+                ((index + 1) as f64 * overlap).floor() > (index as f64 * overlap).floor()
+            }
+            (Some(overlap), None) => rng.gen_bool(overlap),
+        };
+
+        let values = if hit { &self.members } else { &self.decoys };
+
+        match (overlap, index) {
+            (None, Some(index)) if !values.is_empty() => {
+                values.get(index as usize % values.len()).cloned()
+            }
+            _ => values.choose(rng).cloned(),
+        }
+    }
+}
@@ -14,11 +14,25 @@ pub async fn prepare_indices(
     for request in document_creation_requests.iter() {
         let template_file = read_to_string(&request.template).await?;
         let template: serde_json::Value = serde_json::from_str(&template_file)?;
-        let index_definition = template.get("index");
+        match template.get("index_template") {
+            Some(index_template) => {
+                ensure
+                    .ensure_data_stream(
+                        &request.index,
+                        index_template,
+                        template.get("component_templates"),
+                        append,
+                    )
+                    .await?
+            }
+            None => {
+                let index_definition = template.get("index");
 
-        ensure
-            .ensure_index(&request.index, index_definition, append)
-            .await?;
+                ensure
+                    .ensure_index(&request.index, index_definition, append)
+                    .await?
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,53 @@
+use serde_json::Value;
+
+/// Document template compiled once by the `DocumentRenderer` and evaluated for every document
+#[derive(Debug)]
+pub enum RenderPlan {
+    /// Value without placeholders, copied as-is
+    Literal(Value),
+    /// String leaf pre-parsed into the renderer under `name`, `path` points to it in error messages
+    Template {
+        name: String,
+        typed: bool,
+        path: String,
+    },
+    Array(Vec<RenderPlan>),
+    /// Object fields, keys may contain placeholders too
+    Object(Vec<(RenderPlan, RenderPlan)>),
+    /// `{"$repeat": ...}` directive: array of `value` rendered `count` times for every document
+    Repeat {
+        count: RepeatCount,
+        value: Box<RenderPlan>,
+        path: String,
+    },
+    /// `{"$optional": ...}` directive: `value` with probability `probability`, `null` with
+    /// probability `null_probability`, otherwise missing from its object or array
+    Optional {
+        probability: f64,
+        null_probability: f64,
+        value: Box<RenderPlan>,
+    },
+    /// `{"$unique": ...}` directive: `value` rendered again until it differs from every value
+    /// rendered so far into the same `set` during the run
+    Unique {
+        set: String,
+        attempts: usize,
+        value: Box<RenderPlan>,
+        path: String,
+    },
+}
+
+/// Number of items of a `$repeat` directive
+#[derive(Debug)]
+pub enum RepeatCount {
+    /// Random number between both bounds, inclusive
+    Between(u64, u64),
+    /// Number rendered from a template, eg. `"{{ poisson(lambda=3) }}"`
+    Rendered(Box<RenderPlan>),
+}
+
+impl RenderPlan {
+    pub fn is_literal(&self) -> bool {
+        matches!(self, RenderPlan::Literal(_))
+    }
+}
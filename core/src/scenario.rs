@@ -0,0 +1,82 @@
+use anyhow::{anyhow, Result};
+use chrono::Duration;
+use rand::Rng;
+use serde_json::Value;
+
+use crate::{
+    locale::Locale,
+    render_plan::RenderPlan,
+    time_model::{parse_duration, TimeModel},
+};
+
+/// Fixture template describing sequences of related events, eg. a login, then a process start,
+/// then a network connection of the same user on the same host. Each sequence renders every step
+/// once, in order, with the `state` shared by all of them.
+#[derive(Debug)]
+pub struct Scenario {
+    /// Values rendered once per sequence, exposed to the steps as `state`
+    pub state: RenderPlan,
+    pub steps: Vec<Step>,
+    /// Model of the start time of the sequences
+    pub time: Option<TimeModel>,
+    /// Default locale of the faker-backed generators for this fixture
+    pub locale: Option<Locale>,
+}
+
+/// Event of a scenario
+#[derive(Debug)]
+pub struct Step {
+    /// Time elapsed since the previous step, or since the start of the sequence for the first one
+    pub gap: Gap,
+    pub values: RenderPlan,
+}
+
+/// Random duration between two bounds, declared as `"30s"` or `{"min": "1s", "max": "2m"}`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gap {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Default for Gap {
+    fn default() -> Self {
+        Self {
+            min: Duration::zero(),
+            max: Duration::zero(),
+        }
+    }
+}
+
+impl Gap {
+    pub fn from_definition(definition: &Value) -> Result<Self> {
+        let duration = |value: &Value| -> Result<Duration> {
+            value
+                .as_str()
+                .and_then(|duration| parse_duration(duration.trim()))
+                .ok_or(anyhow!(
+                    "gap {} should be a duration like \"500ms\", \"30s\" or \"5m\"",
+                    value
+                ))
+        };
+
+        let (min, max) = match definition {
+            Value::Object(bounds) => (
+                duration(bounds.get("min").unwrap_or(&Value::Null))?,
+                duration(bounds.get("max").unwrap_or(&Value::Null))?,
+            ),
+            gap => (duration(gap)?, duration(gap)?),
+        };
+
+        if min > max {
+            return Err(anyhow!("gap 'min' should not be greater than 'max'"));
+        }
+
+        Ok(Self { min, max })
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Duration {
+        Duration::milliseconds(
+            rng.gen_range(self.min.num_milliseconds()..=self.max.num_milliseconds()),
+        )
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc, Weekday};
+use rand::Rng;
+
+/// Relative activity per hour of the day (UTC), peaking during working hours
+const HOURLY_WEIGHTS: [f64; 24] = [
+    0.2, 0.15, 0.1, 0.1, 0.1, 0.15, 0.3, 0.5, 0.8, 1.0, 1.0, 0.95, 0.9, 0.95, 1.0, 1.0, 0.9, 0.8,
+    0.6, 0.5, 0.4, 0.35, 0.3, 0.25,
+];
+
+/// Relative activity during weekends, compared to weekdays
+const WEEKEND_WEIGHT: f64 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Timestamps spread randomly over the window
+    Uniform,
+    /// Timestamps growing with the document sequence number, from start to end
+    Increasing,
+    /// Random timestamps, denser during working hours and weekdays
+    Diurnal,
+}
+
+/// Fixture-level time model, declared as `"time": {"start": ..., "end": ..., "distribution": ...}`
+/// and exposed to the templates as `doc.timestamp`
+#[derive(Debug, Clone)]
+pub struct TimeModel {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub distribution: Distribution,
+}
+
+impl TimeModel {
+    pub fn from_definition(definition: &serde_json::Value, now: DateTime<Utc>) -> Result<Self> {
+        let bound = |name: &str, default: &str| -> Result<DateTime<Utc>> {
+            match definition.get(name) {
+                Some(value) => parse_time(
+                    value
+                        .as_str()
+                        .ok_or(anyhow!("time '{}' should be a string", name))?,
+                    now,
+                ),
+                None => parse_time(default, now),
+            }
+        };
+
+        let start = bound("start", "now-1d")?;
+        let end = bound("end", "now")?;
+
+        if start > end {
+            return Err(anyhow!("time 'start' should not be after 'end'"));
+        }
+
+        let distribution = match definition.get("distribution").and_then(|d| d.as_str()) {
+            None | Some("uniform") => Distribution::Uniform,
+            Some("increasing") => Distribution::Increasing,
+            Some("diurnal") => Distribution::Diurnal,
+            Some(other) => {
+                return Err(anyhow!(
+                    "unknown time distribution \"{}\", expected uniform, increasing or diurnal",
+                    other
+                ))
+            }
+        };
+
+        Ok(Self {
+            start,
+            end,
+            distribution,
+        })
+    }
+
+    /// Timestamp of the `seq`-th document out of `count`
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, seq: usize, count: usize) -> DateTime<Utc> {
+        let span = (self.end - self.start).num_milliseconds();
+
+        if span <= 0 {
+            return self.start;
+        }
+
+        match self.distribution {
+            Distribution::Uniform => self.start + Duration::milliseconds(rng.gen_range(0..span)),
+            Distribution::Increasing => {
+                // random position within the document's own slot keeps the sequence monotonic
+                let slot = (seq as f64 + rng.gen::<f64>()) / count.max(1) as f64;
+
+                self.start + Duration::milliseconds((span as f64 * slot.min(1.0)) as i64)
+            }
+            Distribution::Diurnal => {
+                let mut candidate = self.start;
+
+                // rejection sampling, every candidate has a chance of at least 4%
+                for _ in 0..1000 {
+                    candidate = self.start + Duration::milliseconds(rng.gen_range(0..span));
+
+                    if rng.gen::<f64>() < diurnal_weight(candidate) {
+                        break;
+                    }
+                }
+
+                candidate
+            }
+        }
+    }
+}
+
+fn diurnal_weight(time: DateTime<Utc>) -> f64 {
+    let weekday_weight = match time.weekday() {
+        Weekday::Sat | Weekday::Sun => WEEKEND_WEIGHT,
+        _ => 1.0,
+    };
+
+    HOURLY_WEIGHTS[time.hour() as usize] * weekday_weight
+}
+
+/// Parses an absolute RFC 3339 date or an expression relative to `now`, eg. `now`, `now-15m`,
+/// `now+1h`. Supported units are ms, s, m, h, d and w.
+pub fn parse_time(expression: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let expression = expression.trim();
+
+    let offset = match expression.strip_prefix("now") {
+        Some(offset) => offset.trim(),
+        None => {
+            return DateTime::parse_from_rfc3339(expression)
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|err| anyhow!("invalid date \"{}\": {}", expression, err))
+        }
+    };
+
+    if offset.is_empty() {
+        return Ok(now);
+    }
+
+    let invalid = || anyhow!("invalid relative date \"{}\"", expression);
+
+    let (sign, amount) = match offset.split_at(1) {
+        ("+", amount) => (1, amount),
+        ("-", amount) => (-1, amount),
+        _ => return Err(invalid()),
+    };
+
+    Ok(now + parse_duration(amount.trim()).ok_or_else(invalid)? * sign)
+}
+
+/// Parses a duration like `15m`, `90s` or `2d`
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let unit_start = duration.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = duration.split_at(unit_start);
+    let amount: i64 = amount.parse().ok()?;
+
+    match unit {
+        "ms" => Some(Duration::milliseconds(amount)),
+        "s" => Some(Duration::seconds(amount)),
+        "m" => Some(Duration::minutes(amount)),
+        "h" => Some(Duration::hours(amount)),
+        "d" => Some(Duration::days(amount)),
+        "w" => Some(Duration::weeks(amount)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Utc};
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+
+    use super::{parse_time, Distribution, TimeModel};
+
+    fn now() -> DateTime<Utc> {
+        "2022-11-20T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn it_parses_absolute_and_relative_dates() {
+        assert_eq!(parse_time("now", now()).unwrap(), now());
+        assert_eq!(
+            parse_time("now-15m", now()).unwrap(),
+            now() - Duration::minutes(15)
+        );
+        assert_eq!(
+            parse_time("now+2d", now()).unwrap(),
+            now() + Duration::days(2)
+        );
+        assert_eq!(
+            parse_time("2022-11-01T00:00:00+01:00", now()).unwrap(),
+            "2022-10-31T23:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert!(parse_time("now-15x", now()).is_err());
+        assert!(parse_time("yesterday", now()).is_err());
+    }
+
+    #[test]
+    fn it_keeps_increasing_timestamps_in_order_within_the_window() {
+        let model = TimeModel::from_definition(
+            &json!({ "start": "now-7d", "distribution": "increasing" }),
+            now(),
+        )
+        .unwrap();
+
+        assert_eq!(model.distribution, Distribution::Increasing);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let timestamps: Vec<_> = (0..1000)
+            .map(|seq| model.sample(&mut rng, seq, 1000))
+            .collect();
+
+        assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(timestamps[0] >= now() - Duration::days(7));
+        assert!(timestamps[999] <= now());
+    }
+}
@@ -2,4 +2,5 @@
 export THREATS=1000 
 export EVENTS=10000
 
-fakebeat ~/projects/fakebeat/examples/threat_url.json -i logs-ti_test_url -c $THREATS ~/projects/fakebeat/examples/log_url.json -i filebeat-url -c $EVENTS
\ No newline at end of file
+# every threat indicator url comes from the "urls" pool, 10% of the events match one of them
+fakebeat ~/projects/fakebeat/examples/threat_url.json -i logs-ti_test_url -c $THREATS ~/projects/fakebeat/examples/log_url.json -i filebeat-url -c $EVENTS